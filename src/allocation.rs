@@ -0,0 +1,469 @@
+use serde::Serialize;
+
+use crate::{
+    rational::Rational,
+    wagon::{TrainFilters, WAGON_INSERTERS},
+    IngredientRatio,
+};
+
+/// Apportion a whole number of slots between shares using the largest remainder method. Every slot is handed out, so the returned counts always sum to `slots` (unless every share is zero). Ties between equal remainders go to the share given first.
+///
+/// # Parameters
+/// * `shares` - The share of each entry. These do not need to sum to 1, as they are normalized against their total.
+/// * `slots` - The number of slots to hand out.
+///
+/// # Returns
+/// The number of slots given to each share, in the same order as `shares`.
+pub fn apportion(shares: &[f64], slots: u32) -> Vec<u32> {
+    let total: f64 = shares.iter().sum();
+    if total <= 0.0 {
+        return vec![0; shares.len()];
+    }
+
+    // Give each share the whole part of its quota first.
+    let quotas: Vec<f64> = shares
+        .iter()
+        .map(|share| share / total * slots as f64)
+        .collect();
+    let mut counts: Vec<u32> = quotas.iter().map(|quota| quota.floor() as u32).collect();
+
+    // Hand out the remaining slots to the largest fractional remainders.
+    let mut order: Vec<usize> = (0..shares.len()).collect();
+    order.sort_by(|&a, &b| {
+        let remainder_a = quotas[a] - quotas[a].floor();
+        let remainder_b = quotas[b] - quotas[b].floor();
+        remainder_b.total_cmp(&remainder_a)
+    });
+
+    let handed_out: u32 = counts.iter().sum();
+    for &index in order.iter().take(slots.saturating_sub(handed_out) as usize) {
+        counts[index] += 1;
+    }
+
+    counts
+}
+
+/// What each slot of an [Allocation] holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Basis {
+    /// Each slot holds one full stack of its ingredient, so slots are handed out by stack ratio.
+    Stacks,
+    /// Each slot carries an equal share of items regardless of stack size (e.g. an inserter), so slots are handed out by item ratio.
+    Items,
+}
+
+/// Apportion a whole number of slots between exact shares using the largest remainder method. This behaves the same as [apportion], except that quotas and remainders are compared exactly, so ties are only ever broken by the order of the shares.
+///
+/// # Returns
+/// The number of slots given to each share as [Some]\(counts), or [None] if the shares are too large to calculate exactly.
+pub fn apportion_exact(shares: &[Rational], slots: u32) -> Option<Vec<u32>> {
+    let quotas = exact_quotas(shares, slots)?;
+    if quotas.iter().all(|quota| *quota == Rational::ZERO) {
+        return Some(vec![0; shares.len()]);
+    }
+
+    // Give each share the whole part of its quota first.
+    let mut counts: Vec<u32> = quotas.iter().map(|quota| quota.floor() as u32).collect();
+
+    // Hand out the remaining slots to the largest fractional remainders. The sort is stable, so equal remainders stay in the order given.
+    let mut order: Vec<usize> = (0..shares.len()).collect();
+    order.sort_by(|&a, &b| quotas[b].fract().cmp(&quotas[a].fract()));
+
+    let handed_out: u32 = counts.iter().sum();
+    for &index in order.iter().take(slots.saturating_sub(handed_out) as usize) {
+        counts[index] += 1;
+    }
+
+    Some(counts)
+}
+
+/// The exact unrounded number of slots each share asks for, with the shares normalized against their total (or none for any share if every share is zero).
+///
+/// # Returns
+/// The quota of each share as [Some]\(quotas), or [None] if the shares are too large to calculate exactly.
+fn exact_quotas(shares: &[Rational], slots: u32) -> Option<Vec<Rational>> {
+    let total = shares
+        .iter()
+        .try_fold(Rational::ZERO, |total, share| total.checked_add(*share))?;
+    if total <= Rational::ZERO {
+        return Some(vec![Rational::ZERO; shares.len()]);
+    }
+
+    let slots = Rational::from_integer(slots as i128);
+    shares
+        .iter()
+        .map(|share| share.checked_div(total)?.checked_mul(slots))
+        .collect()
+}
+
+/// Apportion slots between ingredients by one of their ratios, exactly if every ingredient has an exact ratio and it can be calculated, or in floating point otherwise.
+///
+/// # Parameters
+/// * `item_ratios` - The ingredients to apportion slots between.
+/// * `slots` - The number of slots to hand out.
+/// * `basis` - Which ratio of the ingredients to apportion by.
+fn apportion_ratios(item_ratios: &[IngredientRatio], slots: u32, basis: Basis) -> Vec<u32> {
+    let exact_shares: Option<Vec<Rational>> = item_ratios
+        .iter()
+        .map(|item| {
+            item.exact.as_ref().map(|exact| match basis {
+                Basis::Stacks => exact.stack_ratio,
+                Basis::Items => exact.ratio,
+            })
+        })
+        .collect();
+
+    if let Some(counts) = exact_shares.and_then(|shares| apportion_exact(&shares, slots)) {
+        return counts;
+    }
+
+    let shares: Vec<f64> = item_ratios
+        .iter()
+        .map(|item| match basis {
+            Basis::Stacks => item.stack_ratio,
+            Basis::Items => item.ratio,
+        })
+        .collect();
+    apportion(&shares, slots)
+}
+
+/// Slots of a train configuration handed out between ingredients.
+#[derive(Debug, Clone)]
+pub struct Allocation {
+    /// The total number of slots in the configuration.
+    pub slots: u32,
+    /// The number of slots given to each ingredient, in the same order as the ingredients they were allocated from.
+    pub counts: Vec<u32>,
+    /// What each slot holds.
+    pub basis: Basis,
+    /// The number of groups of slots following the same allocation. This is 1 unless every wagon follows the same filter ("SC" filtering).
+    pub groups: u32,
+}
+
+impl Allocation {
+    /// Allocate slots between ingredients by their stack ratios, with each slot free to take any ingredient ("DC" filtering).
+    ///
+    /// # Parameters
+    /// * `item_ratios` - The ingredients to allocate slots to.
+    /// * `slots` - The total number of slots to allocate.
+    pub fn new(item_ratios: &[IngredientRatio], slots: u32) -> Self {
+        Allocation {
+            slots,
+            counts: apportion_ratios(item_ratios, slots, Basis::Stacks),
+            basis: Basis::Stacks,
+            groups: 1,
+        }
+    }
+
+    /// Allocate the inserters along one side of a train's unloading station, with the inserters beside each wagon handed out between the items that wagon holds (see [TrainFilters::inserters]). Each inserter carries an equal share of items.
+    ///
+    /// # Parameters
+    /// * `item_ratios` - The ingredients the train's filters were planned for.
+    /// * `train` - The planned filters of every wagon of the train.
+    pub fn unloading(item_ratios: &[IngredientRatio], train: &TrainFilters) -> Self {
+        Allocation {
+            slots: WAGON_INSERTERS.saturating_mul(train.cars),
+            counts: train.inserters(item_ratios),
+            basis: Basis::Items,
+            groups: 1,
+        }
+    }
+
+    /// Allocate slots between ingredients by their stack ratios, with every group of `group_slots` slots following the same allocation ("SC" filtering).
+    ///
+    /// # Parameters
+    /// * `item_ratios` - The ingredients to allocate slots to.
+    /// * `group_slots` - The number of slots in each group (e.g. one cargo wagon).
+    /// * `groups` - The number of groups following the same allocation.
+    pub fn repeated(item_ratios: &[IngredientRatio], group_slots: u32, groups: u32) -> Self {
+        let group = Self::new(item_ratios, group_slots);
+        Allocation {
+            slots: group_slots.saturating_mul(groups),
+            counts: group
+                .counts
+                .iter()
+                .map(|count| count.saturating_mul(groups))
+                .collect(),
+            basis: Basis::Stacks,
+            groups,
+        }
+    }
+
+    /// The unrounded number of slots of one group each ingredient's ratio asks for, before slots are apportioned.
+    pub fn quotas(&self, item_ratios: &[IngredientRatio]) -> Vec<f64> {
+        let group_slots = (self.slots / self.groups) as f64;
+        item_ratios
+            .iter()
+            .map(|item| match self.basis {
+                Basis::Stacks => item.stack_ratio * group_slots,
+                Basis::Items => item.ratio * group_slots,
+            })
+            .collect()
+    }
+
+    /// The exact unrounded number of slots of one group each ingredient's ratio asks for, if the slots were apportioned exactly. This is [None] when calculating in floating point, or if the quotas are too large to calculate exactly, in which case the slots were apportioned by [Allocation::quotas].
+    pub fn exact_quotas(&self, item_ratios: &[IngredientRatio]) -> Option<Vec<Rational>> {
+        let shares: Vec<Rational> = item_ratios
+            .iter()
+            .map(|item| {
+                item.exact.as_ref().map(|exact| match self.basis {
+                    Basis::Stacks => exact.stack_ratio,
+                    Basis::Items => exact.ratio,
+                })
+            })
+            .collect::<Option<_>>()?;
+        exact_quotas(&shares, self.slots / self.groups)
+    }
+
+    /// The number of items of each ingredient delivered by this allocation. For allocations by [Basis::Items], this is the number of slots, as each slot carries an equal share of items.
+    pub fn delivered_items(&self, item_ratios: &[IngredientRatio]) -> Vec<f64> {
+        self.counts
+            .iter()
+            .zip(item_ratios)
+            .map(|(count, item)| match self.basis {
+                Basis::Stacks => *count as f64 * item.ingredient.stack_size as f64,
+                Basis::Items => *count as f64,
+            })
+            .collect()
+    }
+
+    /// The item ratio of each ingredient actually delivered by this allocation. This value is between 0 and 1.
+    pub fn delivered_ratios(&self, item_ratios: &[IngredientRatio]) -> Vec<f64> {
+        let delivered = self.delivered_items(item_ratios);
+        let total: f64 = delivered.iter().sum();
+        delivered
+            .iter()
+            .map(|items| if total > 0.0 { items / total } else { 0.0 })
+            .collect()
+    }
+
+    /// The absolute error of each ingredient's delivered ratio against its requested ratio (e.g. 0.01 if an ingredient makes up one percentage point more of the delivery than requested).
+    pub fn ratio_deviations(&self, item_ratios: &[IngredientRatio]) -> Vec<f64> {
+        self.delivered_ratios(item_ratios)
+            .iter()
+            .zip(item_ratios)
+            .map(|(delivered, item)| delivered - item.ratio)
+            .collect()
+    }
+
+    /// The relative error of each ingredient's delivered ratio against its requested ratio (e.g. 0.1 if an ingredient is delivered 10% more than requested, or -0.1 if 10% less).
+    pub fn ratio_errors(&self, item_ratios: &[IngredientRatio]) -> Vec<f64> {
+        self.delivered_ratios(item_ratios)
+            .iter()
+            .zip(item_ratios)
+            .map(|(delivered, item)| delivered / item.ratio - 1.0)
+            .collect()
+    }
+
+    /// The largest relative error of any ingredient's delivered ratio against its requested ratio, as a positive number.
+    pub fn max_ratio_error(&self, item_ratios: &[IngredientRatio]) -> f64 {
+        self.ratio_errors(item_ratios)
+            .iter()
+            .fold(0.0, |max, error| f64::max(max, error.abs()))
+    }
+
+    /// The index of the ingredient that runs out first when the delivered items are used up in the requested ratio, or [None] if there are no ingredients.
+    pub fn limiting_ingredient(&self, item_ratios: &[IngredientRatio]) -> Option<usize> {
+        self.delivered_items(item_ratios)
+            .iter()
+            .zip(item_ratios)
+            .map(|(delivered, item)| delivered / item.ingredient.amount.to_f64())
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index)
+    }
+
+    /// The number of slots holding items left over once the limiting ingredient runs out, when the delivered items are used up in the requested ratio.
+    pub fn wasted_slots(&self, item_ratios: &[IngredientRatio]) -> f64 {
+        let delivered = self.delivered_items(item_ratios);
+
+        // The number of times the requested amounts fit into the delivered items.
+        let sets = delivered
+            .iter()
+            .zip(item_ratios)
+            .map(|(delivered, item)| delivered / item.ingredient.amount.to_f64())
+            .fold(f64::INFINITY, f64::min);
+        if !sets.is_finite() {
+            return 0.0;
+        }
+
+        let used_slots: f64 = item_ratios
+            .iter()
+            .map(|item| match self.basis {
+                Basis::Stacks => {
+                    sets * item.ingredient.amount.to_f64() / item.ingredient.stack_size as f64
+                }
+                Basis::Items => sets * item.ingredient.amount.to_f64(),
+            })
+            .sum();
+        self.slots as f64 - used_slots
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{calculate_ratios, Ingredient};
+
+    /// Exact shares from whole numbers.
+    fn exact_shares(shares: &[i128]) -> Vec<Rational> {
+        shares
+            .iter()
+            .map(|share| Rational::from_integer(*share))
+            .collect()
+    }
+
+    #[test]
+    fn apportion_hands_out_every_slot() {
+        for slots in 0..=100 {
+            let counts = apportion(&[1.0, 2.0, 3.0, 7.0], slots);
+            assert_eq!(counts.iter().sum::<u32>(), slots, "{slots} slots");
+        }
+    }
+
+    #[test]
+    fn apportion_gives_leftover_slots_to_largest_remainders() {
+        // Quotas of 0.7, 2.1 and 4.2 round down to 6 slots, leaving the 7th to the 0.7 remainder.
+        assert_eq!(apportion(&[1.0, 3.0, 6.0], 7), vec![1, 2, 4]);
+        assert_eq!(
+            apportion_exact(&exact_shares(&[1, 3, 6]), 7),
+            Some(vec![1, 2, 4])
+        );
+    }
+
+    #[test]
+    fn apportion_breaks_ties_in_order_given() {
+        assert_eq!(apportion(&[1.0, 1.0, 1.0], 40), vec![14, 13, 13]);
+        assert_eq!(apportion(&[2.0, 1.0, 1.0], 6), vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn apportion_of_nothing_is_nothing() {
+        assert_eq!(apportion(&[0.0, 0.0], 40), vec![0, 0]);
+        assert_eq!(apportion(&[], 40), Vec::<u32>::new());
+        assert_eq!(
+            apportion_exact(&exact_shares(&[0, 0]), 40),
+            Some(vec![0, 0])
+        );
+    }
+
+    #[test]
+    fn apportion_exact_hands_out_every_slot() {
+        let shares = exact_shares(&[1, 2, 3, 7]);
+        for slots in 0..=100 {
+            let counts = apportion_exact(&shares, slots).unwrap();
+            assert_eq!(counts.iter().sum::<u32>(), slots, "{slots} slots");
+        }
+    }
+
+    #[test]
+    fn apportion_exact_breaks_ties_in_order_given() {
+        let third = Rational::new(1, 3).unwrap();
+        assert_eq!(
+            apportion_exact(&[third, third, third], 40),
+            Some(vec![14, 13, 13])
+        );
+        assert_eq!(
+            apportion_exact(&exact_shares(&[2, 1, 1]), 6),
+            Some(vec![3, 2, 1])
+        );
+    }
+
+    #[test]
+    fn apportion_exact_reports_overflow() {
+        let shares = exact_shares(&[i128::MAX, i128::MAX]);
+        assert_eq!(apportion_exact(&shares, 40), None);
+    }
+
+    #[test]
+    fn exact_and_floating_point_apportionment_agree() {
+        let weights = [1, 2, 3, 7, 11, 50];
+        let shares: Vec<f64> = weights.iter().map(|weight| *weight as f64).collect();
+        let exact = exact_shares(&weights);
+        for slots in 0..=200 {
+            assert_eq!(
+                Some(apportion(&shares, slots)),
+                apportion_exact(&exact, slots),
+                "{slots} slots"
+            );
+        }
+    }
+
+    #[test]
+    fn allocations_agree_whether_calculated_exactly_or_not() {
+        let ingredients = vec![
+            Ingredient::new("iron plate", Rational::from_integer(1200), 100).unwrap(),
+            Ingredient::new("copper cable", Rational::from_integer(2000), 200).unwrap(),
+            Ingredient::new("coal", Rational::new(50, 3).unwrap(), 50).unwrap(),
+        ];
+        let float = calculate_ratios(&ingredients, false).unwrap();
+        let exact = calculate_ratios(&ingredients, true).unwrap();
+        for slots in [40, 80, 160, 288, 27] {
+            assert_eq!(
+                Allocation::new(&float, slots).counts,
+                Allocation::new(&exact, slots).counts
+            );
+        }
+    }
+
+    #[test]
+    fn repeated_allocations_multiply_one_group() {
+        let ingredients = vec![
+            Ingredient::new("iron plate", Rational::from_integer(100), 100).unwrap(),
+            Ingredient::new("copper plate", Rational::from_integer(200), 100).unwrap(),
+        ];
+        let item_ratios = calculate_ratios(&ingredients, false).unwrap();
+        let allocation = Allocation::repeated(&item_ratios, 40, 3);
+        assert_eq!(allocation.slots, 120);
+        assert_eq!(allocation.groups, 3);
+        assert_eq!(allocation.counts, vec![39, 81]);
+    }
+
+    #[test]
+    fn reports_the_error_of_the_delivered_ratio() {
+        let ingredients = vec![
+            Ingredient::new("iron plate", Rational::from_integer(100), 100).unwrap(),
+            Ingredient::new("copper plate", Rational::from_integer(200), 100).unwrap(),
+        ];
+        let item_ratios = calculate_ratios(&ingredients, false).unwrap();
+
+        // Quotas of 13.33 and 26.67 stacks round to 13 and 27.
+        let allocation = Allocation::new(&item_ratios, 40);
+        assert_eq!(allocation.counts, vec![13, 27]);
+        assert_eq!(
+            allocation.delivered_items(&item_ratios),
+            vec![1300.0, 2700.0]
+        );
+
+        let close = |actual: Vec<f64>, expected: [f64; 2]| {
+            for (actual, expected) in actual.iter().zip(expected) {
+                assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+            }
+        };
+        close(allocation.delivered_ratios(&item_ratios), [0.325, 0.675]);
+        close(
+            allocation.ratio_deviations(&item_ratios),
+            [-1.0 / 120.0, 1.0 / 120.0],
+        );
+        close(allocation.ratio_errors(&item_ratios), [-0.025, 0.0125]);
+        assert!((allocation.max_ratio_error(&item_ratios) - 0.025).abs() < 1e-9);
+
+        // 13 sets of 1 iron stack to 2 copper stacks use 39 of the 40 slots.
+        assert_eq!(allocation.limiting_ingredient(&item_ratios), Some(0));
+        assert!((allocation.wasted_slots(&item_ratios) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_perfect_allocation_has_no_error() {
+        let ingredients = vec![
+            Ingredient::new("iron plate", Rational::from_integer(100), 100).unwrap(),
+            Ingredient::new("copper plate", Rational::from_integer(300), 100).unwrap(),
+        ];
+        let item_ratios = calculate_ratios(&ingredients, false).unwrap();
+        let allocation = Allocation::new(&item_ratios, 40);
+        assert_eq!(allocation.counts, vec![10, 30]);
+        assert_eq!(allocation.max_ratio_error(&item_ratios), 0.0);
+        assert_eq!(allocation.wasted_slots(&item_ratios), 0.0);
+    }
+}
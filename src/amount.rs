@@ -0,0 +1,393 @@
+use std::fmt::Display;
+
+use crate::rational::Rational;
+
+/// The unit an amount is typed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantity {
+    /// A number of items.
+    Items,
+    /// A number of stacks of the item, converted to items with its stack size.
+    Stacks,
+}
+
+/// The time an amount is typed over, if it is a rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Per {
+    /// A rate per second.
+    Second,
+    /// A rate per minute, converted to a rate per second.
+    Minute,
+}
+
+/// An amount as typed into the amount prompt, before it is converted to items with the item's stack size: the value of an arithmetic expression (e.g. "3*450" or "2.5k"), in items or stacks, and optionally a rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AmountInput {
+    /// The value of the expression.
+    pub value: Rational,
+    /// The unit of the value.
+    pub quantity: Quantity,
+    /// The time the value is over, if it is a rate.
+    pub per: Option<Per>,
+}
+
+/// An amount of an item, converted to items or to items per second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Amount {
+    /// The number of items, or items per second if the amount is a rate.
+    pub value: Rational,
+    /// Whether the amount is a rate.
+    pub rate: bool,
+}
+
+impl Display for Amount {
+    /// Format the amount with its unit (e.g. "1200 items" or "15 items/s").
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.rate {
+            true => write!(f, "{} items/s", self.value),
+            false => write!(f, "{} items", self.value),
+        }
+    }
+}
+
+impl AmountInput {
+    /// Convert the amount to items, or to items per second if it is a rate.
+    ///
+    /// # Parameters
+    /// * `stack_size` - The stack size of the item, to convert stacks to items with.
+    ///
+    /// # Returns
+    /// The amount as [Some]\(amount), or [None] if it is too large to convert.
+    pub fn to_amount(self, stack_size: u32) -> Option<Amount> {
+        let mut value = self.value;
+        if self.quantity == Quantity::Stacks {
+            value = value.checked_mul(Rational::from_integer(stack_size as i128))?;
+        }
+        if self.per == Some(Per::Minute) {
+            value = value.checked_div(Rational::from_integer(60))?;
+        }
+
+        Some(Amount {
+            value,
+            rate: self.per.is_some(),
+        })
+    }
+}
+
+impl std::str::FromStr for AmountInput {
+    type Err = String;
+
+    /// Parse an amount from an arithmetic expression of numbers (e.g. "3*450", "1200/4+50" or "(2+1)*100"), where numbers may end in "k" for thousands or "m" for millions, optionally followed by "stacks" (or "items") and a rate of "/s" or "/min" (e.g. "12 stacks", "15/s" or "2.5k/min").
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut expression = s.trim().to_lowercase();
+
+        // A trailing "/s" or "/min" is a rate rather than a division.
+        let mut per = None;
+        if let Some((rest, unit)) = expression.rsplit_once('/') {
+            per = match unit.trim() {
+                "s" | "sec" | "second" => Some(Per::Second),
+                "m" | "min" | "minute" => Some(Per::Minute),
+                _ => None,
+            };
+            if per.is_some() {
+                expression = rest.trim_end().to_string();
+            }
+        }
+
+        let mut quantity = Quantity::Items;
+        for (suffix, unit) in [
+            ("stacks", Quantity::Stacks),
+            ("stack", Quantity::Stacks),
+            ("items", Quantity::Items),
+            ("item", Quantity::Items),
+        ] {
+            if let Some(rest) = expression.strip_suffix(suffix) {
+                expression = rest.trim_end().to_string();
+                quantity = unit;
+                break;
+            }
+        }
+
+        let mut parser = ExpressionParser {
+            chars: expression.chars().collect(),
+            position: 0,
+        };
+        let value = parser.parse_sum()?;
+        parser.skip_whitespace();
+        if let Some(c) = parser.peek() {
+            return Err(format!("unexpected \"{c}\" in amount"));
+        }
+
+        Ok(AmountInput {
+            value,
+            quantity,
+            per,
+        })
+    }
+}
+
+/// A recursive descent parser of arithmetic expressions of exact rational numbers.
+struct ExpressionParser {
+    /// The characters of the expression.
+    chars: Vec<char>,
+    /// The index of the next character to read.
+    position: usize,
+}
+
+impl ExpressionParser {
+    /// The next character that is not whitespace, without reading it.
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.get(self.position).copied()
+    }
+
+    /// Skip past any whitespace.
+    fn skip_whitespace(&mut self) {
+        while self
+            .chars
+            .get(self.position)
+            .is_some_and(|c| c.is_whitespace())
+        {
+            self.position += 1;
+        }
+    }
+
+    /// Parse terms added to or subtracted from each other (e.g. "1200+3*50").
+    fn parse_sum(&mut self) -> Result<Rational, String> {
+        let mut value = self.parse_product()?;
+        while let Some(operator @ ('+' | '-')) = self.peek() {
+            self.position += 1;
+            let term = self.parse_product()?;
+            value = match operator {
+                '+' => value.checked_add(term),
+                _ => value.checked_sub(term),
+            }
+            .ok_or("amount is too large")?;
+        }
+        Ok(value)
+    }
+
+    /// Parse factors multiplied or divided by each other (e.g. "3*450/2").
+    fn parse_product(&mut self) -> Result<Rational, String> {
+        let mut value = self.parse_factor()?;
+        while let Some(operator @ ('*' | 'x' | '/')) = self.peek() {
+            self.position += 1;
+            let factor = self.parse_factor()?;
+            value = match operator {
+                '/' if factor == Rational::ZERO => return Err("division by zero".into()),
+                '/' => value.checked_div(factor),
+                _ => value.checked_mul(factor),
+            }
+            .ok_or("amount is too large")?;
+        }
+        Ok(value)
+    }
+
+    /// Parse a number with an optional thousands or millions suffix (e.g. "2.5k"), or an expression in brackets.
+    fn parse_factor(&mut self) -> Result<Rational, String> {
+        match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let value = self.parse_sum()?;
+                match self.peek() {
+                    Some(')') => {
+                        self.position += 1;
+                        Ok(value)
+                    }
+                    _ => Err("missing \")\" in amount".into()),
+                }
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let start = self.position;
+                while self
+                    .chars
+                    .get(self.position)
+                    .is_some_and(|c| c.is_ascii_digit() || *c == '.' || *c == '_' || *c == ',')
+                {
+                    self.position += 1;
+                }
+                // Digit separators (e.g. "1,200" or "1_200") are ignored.
+                let digits: String = self.chars[start..self.position]
+                    .iter()
+                    .filter(|c| **c != '_' && **c != ',')
+                    .collect();
+                let number = digits
+                    .parse::<Rational>()
+                    .map_err(|e| format!("invalid number \"{digits}\": {e}"))?;
+
+                let multiplier = match self.chars.get(self.position) {
+                    Some('k') => 1_000,
+                    Some('m') => 1_000_000,
+                    _ => return Ok(number),
+                };
+                self.position += 1;
+                number
+                    .checked_mul(Rational::from_integer(multiplier))
+                    .ok_or("amount is too large".into())
+            }
+            Some(c) => Err(format!("unexpected \"{c}\" in amount, expected a number")),
+            None => Err("expected an amount (e.g. \"1200\", \"3*450\", \"2.5k\", \"12 stacks\" or \"15/s\")".into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse an amount, panicking if it is invalid.
+    fn parse(input: &str) -> AmountInput {
+        input.parse().unwrap_or_else(|e| panic!("{input:?}: {e}"))
+    }
+
+    /// A rational number from a numerator and a non-zero denominator.
+    fn ratio(numerator: i128, denominator: i128) -> Rational {
+        Rational::new(numerator, denominator).unwrap()
+    }
+
+    #[test]
+    fn parses_plain_numbers_as_items() {
+        let amount = parse("1200");
+        assert_eq!(amount.value, Rational::from_integer(1200));
+        assert_eq!(amount.quantity, Quantity::Items);
+        assert_eq!(amount.per, None);
+        assert_eq!(parse("12.5").value, ratio(25, 2));
+        assert_eq!(parse("1200 items").quantity, Quantity::Items);
+    }
+
+    #[test]
+    fn parses_thousands_and_millions_suffixes() {
+        assert_eq!(parse("2.5k").value, Rational::from_integer(2500));
+        assert_eq!(parse("2K").value, Rational::from_integer(2000));
+        assert_eq!(parse("2m").value, Rational::from_integer(2_000_000));
+        assert_eq!(parse("2m").per, None);
+    }
+
+    #[test]
+    fn parses_digit_separators() {
+        assert_eq!(parse("1,200").value, Rational::from_integer(1200));
+        assert_eq!(parse("1_200_000").value, Rational::from_integer(1_200_000));
+    }
+
+    #[test]
+    fn parses_arithmetic() {
+        assert_eq!(parse("3*450").value, Rational::from_integer(1350));
+        assert_eq!(parse("3x450").value, Rational::from_integer(1350));
+        assert_eq!(parse("1200/4+50").value, Rational::from_integer(350));
+        assert_eq!(parse("(1200+600)/2").value, Rational::from_integer(900));
+        assert_eq!(parse("2+3*4").value, Rational::from_integer(14));
+        assert_eq!(parse("10-4-3").value, Rational::from_integer(3));
+        assert_eq!(parse(" 2 * ( 1 + 2 ) ").value, Rational::from_integer(6));
+    }
+
+    #[test]
+    fn parses_fractions_as_division() {
+        let amount = parse("1/3");
+        assert_eq!(amount.value, ratio(1, 3));
+        assert_eq!(amount.per, None);
+    }
+
+    #[test]
+    fn parses_stacks() {
+        let amount = parse("12 stacks");
+        assert_eq!(amount.value, Rational::from_integer(12));
+        assert_eq!(amount.quantity, Quantity::Stacks);
+        assert_eq!(parse("1 stack").quantity, Quantity::Stacks);
+        assert_eq!(
+            amount.to_amount(50),
+            Some(Amount {
+                value: Rational::from_integer(600),
+                rate: false
+            })
+        );
+    }
+
+    #[test]
+    fn parses_rates_per_second_and_minute() {
+        let per_second = parse("15/s");
+        assert_eq!(per_second.per, Some(Per::Second));
+        assert_eq!(
+            per_second.to_amount(100),
+            Some(Amount {
+                value: Rational::from_integer(15),
+                rate: true
+            })
+        );
+
+        let per_minute = parse("900/min");
+        assert_eq!(per_minute.per, Some(Per::Minute));
+        assert_eq!(
+            per_minute.to_amount(100),
+            Some(Amount {
+                value: Rational::from_integer(15),
+                rate: true
+            })
+        );
+
+        assert_eq!(parse("2.5k / sec").value, Rational::from_integer(2500));
+    }
+
+    #[test]
+    fn tells_millions_from_per_minute() {
+        let millions = parse("2m");
+        assert_eq!(millions.value, Rational::from_integer(2_000_000));
+        assert_eq!(millions.per, None);
+
+        let per_minute = parse("2/m");
+        assert_eq!(per_minute.value, Rational::from_integer(2));
+        assert_eq!(per_minute.per, Some(Per::Minute));
+
+        let millions_per_minute = parse("2m/m");
+        assert_eq!(millions_per_minute.value, Rational::from_integer(2_000_000));
+        assert_eq!(millions_per_minute.per, Some(Per::Minute));
+    }
+
+    #[test]
+    fn parses_stacks_per_minute() {
+        let amount = parse("12 stacks/min");
+        assert_eq!(amount.quantity, Quantity::Stacks);
+        assert_eq!(amount.per, Some(Per::Minute));
+        assert_eq!(
+            amount.to_amount(100),
+            Some(Amount {
+                value: Rational::from_integer(20),
+                rate: true
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_amounts() {
+        for input in ["", "abc", "(1+2", "1+", "2 m", "1..2", "12 crates", "*3"] {
+            assert!(input.parse::<AmountInput>().is_err(), "{input:?}");
+        }
+        assert_eq!(
+            "1/0".parse::<AmountInput>(),
+            Err("division by zero".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_amounts_too_large() {
+        let digits = "9".repeat(30);
+        assert!(format!("{digits}*{digits}").parse::<AmountInput>().is_err());
+        assert_eq!(
+            parse(format!("{digits}k stacks").as_str()).to_amount(u32::MAX),
+            None
+        );
+    }
+
+    #[test]
+    fn displays_amounts_with_their_unit() {
+        let items = Amount {
+            value: Rational::from_integer(1200),
+            rate: false,
+        };
+        let rate = Amount {
+            value: ratio(31, 2),
+            rate: true,
+        };
+        assert_eq!(items.to_string(), "1200 items");
+        assert_eq!(rate.to_string(), "15.5 items/s");
+    }
+}
@@ -0,0 +1,720 @@
+use std::{ops::RangeInclusive, path::PathBuf};
+
+use clap::{Parser, Subcommand};
+
+use crate::{
+    dispatch::{Dispatcher, MAX_TRAIN_LIMIT},
+    merge_ingredients, parse_ingredient_arg,
+    plan::Plan,
+    report::{Chest, Format, Selection, Verbosity},
+    schedule::LoadWait,
+    wagon::MAX_CARS,
+    Ingredient,
+};
+
+/// Factrain is a calculator for Factorio train station item ratios.
+///
+/// Ingredients are passed as arguments, read from a plan file, read line by line from piped input ("NAME,AMOUNT[,STACK]"), or prompted for if none are given.
+///
+/// The older bare-word options (e.g. "factrain sc 4-car extra") are still accepted.
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    arg_required_else_help = true,
+    args_conflicts_with_subcommands = true
+)]
+pub struct Args {
+    /// A command to run instead of calculating ratios.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Cargo wagon counts of the trains to calculate over, up to 100. Can be repeated or separated by commas (e.g. "-c 2,4").
+    #[arg(short, long, value_name = "N", value_delimiter = ',', value_parser = clap::value_parser!(u32).range(1..=MAX_CARS as i64))]
+    pub cars: Vec<u32>,
+
+    /// Same-car wagon filtering. Use this option to restrict each wagon (on one train) to the same filter.
+    #[arg(short = 's', long = "sc")]
+    pub same_car: bool,
+
+    /// Different-car wagon filtering. Use this option if you wish for each wagon (on one train) to be free to choose its own individual filter.
+    #[arg(short = 'd', long = "dc")]
+    pub different_car: bool,
+
+    /// Print some extras.
+    #[arg(short, long)]
+    pub extra: bool,
+
+    /// Print the filter of every slot of every wagon as a grid.
+    #[arg(short = 'g', long)]
+    pub filters: bool,
+
+    /// Print suggested names for the loading and unloading stations of each train, with a rich-text icon of each ingredient (e.g. "[item=iron-plate][item=steel-plate] Drop 4-car").
+    ///
+    /// The icons are ordered by each ingredient's share of the train. Station and schedule blueprints use the same names.
+    #[arg(short = 'n', long)]
+    pub names: bool,
+
+    /// Print every step of the calculation after the usual output: the totals the ratios are calculated from, each ingredient's share of the stacks, and how the slots of every configuration were apportioned and rounded.
+    ///
+    /// For every configuration, the unrounded share of slots of each ingredient is shown, along with which ingredients were rounded up to hand out the slots left over (largest remainder first).
+    #[arg(long)]
+    pub explain: bool,
+
+    /// Dedicate whole wagons to a single ingredient where it fills them (DC filtering only).
+    #[arg(short, long)]
+    pub pure: bool,
+
+    /// Print a blueprint string of each train with the filter of every wagon set, instead of the usual output.
+    ///
+    /// The locomotives are at the front, and the train snaps onto rails when placed. Items are given their internal Factorio names (e.g. "iron-plate"); items factrain does not know are named after their lowercase name with hyphens for spaces, as most mods do.
+    #[arg(short, long)]
+    pub blueprint: bool,
+
+    /// Give each train blueprint a schedule between its loading and unloading stations, waiting at the loading station until every item reaches its planned count ("--schedule" or "--schedule=counts") or the cargo is full ("--schedule=full").
+    #[arg(long, value_name = "WAIT", num_args = 0..=1, require_equals = true, default_missing_value = "counts")]
+    pub schedule: Option<LoadWait>,
+
+    /// Write train schedules as Factorio 2.0 interrupts, sending trains from a depot to load when empty and to unload when loaded.
+    ///
+    /// These blueprints are written for Factorio 2.0, so items renamed in 2.0 are given their new names (e.g. "barrel" for "empty-barrel"), and items removed in 2.0 (the rocket control unit) are rejected.
+    #[arg(long)]
+    pub interrupts: bool,
+
+    /// Print a blueprint string of an unloading station for each train length, with the filter of every inserter and the limit of every chest set, instead of the usual output.
+    #[arg(long)]
+    pub station: bool,
+
+    /// Print a blueprint string of constant combinators for each train, with one combinator per wagon holding the number of each item the wagon is loaded with, instead of the usual output.
+    ///
+    /// Wire each combinator to its wagon's loading inserters to enable each inserter while the wagon holds less than its count.
+    #[arg(long)]
+    pub combinators: bool,
+
+    /// Print the requester and provider station settings of each train for a train dispatcher mod (ltn or cybersyn), followed by a blueprint string of constant combinators holding their signals, instead of the usual output.
+    #[arg(long, value_name = "MOD")]
+    pub dispatcher: Option<Dispatcher>,
+
+    /// The number of trains allowed at each station at once, for --dispatcher [default: 1]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..=MAX_TRAIN_LIMIT as i64))]
+    pub train_limit: Option<u32>,
+
+    /// The number of sides of the rails unloading stations unload from: 1 for the right-hand side only, or 2 for both [default: 1]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..=2))]
+    pub sides: Option<u32>,
+
+    /// The number of locomotives at the front of each train in blueprints, up to 100 [default: 1]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..=MAX_CARS as i64))]
+    pub locomotives: Option<u32>,
+
+    /// Compare the slot allocations of trains from MIN to MAX cargo wagons side by side, instead of printing the usual output. Compares 1 to 8 wagons if no range is given, and up to 100 wagons at most.
+    #[arg(long, value_name = "MIN-MAX", num_args = 0..=1, default_missing_value = "1-8", value_parser = parse_range)]
+    pub compare: Option<RangeInclusive<u32>>,
+
+    /// The largest acceptable ratio error in percent when comparing train lengths [default: 5]
+    #[arg(long, value_name = "PERCENT")]
+    pub target: Option<f64>,
+
+    /// Calculate ratios and allocate slots with exact fractions instead of floating point, so that rounding ties are always broken the same way.
+    #[arg(short = 'x', long)]
+    pub exact: bool,
+
+    /// How much of the output to print: compact (stacks and inserters), normal (adds items, chests and a ratio error summary) or full (adds timings and per-ingredient errors) [default: normal]
+    #[arg(long, value_name = "LEVEL")]
+    pub verbosity: Option<Verbosity>,
+
+    /// Full output of less-commonly-used fields. Short for "--verbosity full".
+    #[arg(short, long, conflicts_with_all = ["verbosity", "compact"])]
+    pub full: bool,
+
+    /// Compact output of only the most-used fields. Short for "--verbosity compact".
+    #[arg(short = 'q', long, conflicts_with = "verbosity")]
+    pub compact: bool,
+
+    /// The format to print the output in: text, json for a machine-readable document of everything calculated, or csv or markdown for a table with one row per ingredient and one column per configuration [default: text]
+    ///
+    /// The JSON document has a "schema_version", the "settings" calculated over, the "ingredients" with their ratios, the "configurations" (train slots, items, chests and inserters) with their slot counts and ratio errors, the slot filters of every wagon under "trains", the unloading "timings", and the "comparison" of train lengths with --compare. Ingredients are referred to by their index into "ingredients", and exact fractions are written as strings (e.g. "1/3"). With --compare, CSV and Markdown tables have one column of wagon slots per train length instead.
+    #[arg(long, value_name = "FORMAT")]
+    pub format: Option<Format>,
+
+    /// The type of chest the train is unloaded into: wooden, iron or steel [default: steel]
+    #[arg(long, value_name = "TYPE")]
+    pub chest: Option<Chest>,
+
+    /// Read ingredients and options from a TOML plan file, or a JSON plan file if it ends in ".json". Options passed alongside it are added to the plan's.
+    #[arg(long, value_name = "FILE", global = true)]
+    pub plan: Option<PathBuf>,
+
+    /// Write the ingredients and options calculated over to a TOML or JSON plan file, to be read back with --plan. With tui, the plan is written as it was left on quitting.
+    #[arg(long, value_name = "FILE", global = true)]
+    pub save: Option<PathBuf>,
+
+    /// Ingredients to calculate over, with an optional stack size override after a colon (e.g. "iron plate=1200" or "steel=300:100").
+    #[arg(value_name = "NAME=AMOUNT[:STACK]", value_parser = parse_ingredient_arg)]
+    pub ingredients: Vec<Ingredient>,
+}
+
+/// Commands run instead of calculating ratios.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Decode a blueprint string of an existing train or station, and print the ratio its wagon filters, inserter filters and chest limits deliver. Pass --plan to compare it against the plan's ingredients.
+    Audit {
+        /// The blueprint string, as exported from the game, or "-" to read it from input.
+        #[arg(value_name = "BLUEPRINT")]
+        blueprint: String,
+    },
+    /// Design the station in a full-screen terminal UI: edit the ingredients beside live wagon filters and chest and inserter allocations, and change the train length, wagon filtering and chest type with keys. Starts from the plan given with --plan, if any.
+    Tui,
+}
+
+impl Args {
+    /// Parse the arguments the program was run with, translating the older bare-word options into their long forms first.
+    pub fn parse_env() -> Result<Args, clap::Error> {
+        Args::try_parse_from(std::env::args().enumerate().map(|(position, arg)| {
+            if position == 0 {
+                arg
+            } else {
+                translate_legacy_arg(arg)
+            }
+        }))
+    }
+}
+
+/// Translate an argument from the older bare-word options (e.g. "4-car" or "compare=2-6") into its long form (e.g. "--cars=4" or "--compare=2-6"). Any other argument is returned as is.
+fn translate_legacy_arg(arg: String) -> String {
+    if let Some(cars) = arg.strip_suffix("-car") {
+        if !cars.is_empty() && cars.chars().all(|c| c.is_ascii_digit()) {
+            return format!("--cars={cars}");
+        }
+    }
+
+    match arg.as_str() {
+        "help" => "--help".into(),
+        "sc" | "dc" | "extra" | "filters" | "pure" | "exact" | "compare" => format!("--{arg}"),
+        _ => match arg.split_once('=') {
+            Some(("compare" | "target" | "chest", _)) => format!("--{arg}"),
+            _ => arg,
+        },
+    }
+}
+
+/// Parse a range of wagon counts in the form "MIN-MAX" (e.g. "2-6"), up to [MAX_CARS] wagons.
+fn parse_range(range: &str) -> Result<RangeInclusive<u32>, String> {
+    let invalid =
+        || format!("expected MIN-MAX with 1 <= MIN <= MAX (e.g. \"2-6\"), got \"{range}\"");
+
+    let (min, max) = range.split_once('-').ok_or_else(invalid)?;
+    let min = min.trim().parse::<u32>().map_err(|_| invalid())?;
+    let max = max.trim().parse::<u32>().map_err(|_| invalid())?;
+    if min == 0 || min > max {
+        return Err(invalid());
+    }
+    if max > MAX_CARS {
+        return Err(format!(
+            "can compare trains of up to {MAX_CARS} cargo wagons, got \"{range}\""
+        ));
+    }
+
+    Ok(min..=max)
+}
+
+/// Everything to calculate over, merged from the arguments and the plan file (if any) and checked to make sense together.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// The train configurations to calculate over.
+    pub selection: Selection,
+    /// Whether to print suggested station names.
+    pub names: bool,
+    /// Whether to print every step of the calculation.
+    pub explain: bool,
+    /// Whether to dedicate whole wagons to a single ingredient where it fills them.
+    pub pure: bool,
+    /// Whether to print the filter of every slot of every wagon.
+    pub filters: bool,
+    /// Whether to print a blueprint string of each train instead of the usual output.
+    pub blueprint: bool,
+    /// How long each train waits at its loading station, if train blueprints are to be given a schedule.
+    pub schedule: Option<LoadWait>,
+    /// Whether to write train schedules as Factorio 2.0 interrupts.
+    pub interrupts: bool,
+    /// Whether to print a blueprint string of an unloading station for each train length instead of the usual output.
+    pub station: bool,
+    /// Whether to print a blueprint string of constant combinators for each train instead of the usual output.
+    pub combinators: bool,
+    /// The train dispatcher mod to print station settings for, if any.
+    pub dispatcher: Option<Dispatcher>,
+    /// The number of trains allowed at each station at once.
+    pub train_limit: u32,
+    /// The number of sides of the rails unloading stations unload from.
+    pub sides: u32,
+    /// The number of locomotives at the front of each train in blueprints.
+    pub locomotives: u32,
+    /// Whether to calculate with exact fractions.
+    pub exact: bool,
+    /// How much of the output to print.
+    pub verbosity: Verbosity,
+    /// The format to print the output in.
+    pub format: Format,
+    /// The range of wagon counts to compare, if comparing train lengths.
+    pub compare: Option<RangeInclusive<u32>>,
+    /// The largest acceptable relative ratio error when comparing train lengths (e.g. 0.05 for 5%).
+    pub target_error: f64,
+    /// The plan file to save to, if any.
+    pub save: Option<PathBuf>,
+    /// The ingredients given in the plan and arguments. If empty, ingredients are to be read from input.
+    pub ingredients: Vec<Ingredient>,
+}
+
+impl Settings {
+    /// Merge the arguments into the plan's options, and check the result makes sense.
+    ///
+    /// # Returns
+    /// The merged settings as [Ok]\(settings), or [Err]\(message) describing what is missing or conflicting.
+    pub fn new(args: Args, plan: &Plan) -> Result<Settings, String> {
+        let mut cars = plan.train.cars.clone();
+        cars.extend(args.cars);
+        cars.sort();
+        cars.dedup();
+
+        let selection = Selection {
+            cars,
+            same_car: plan.train.same_car || args.same_car,
+            different_car: plan.train.different_car || args.different_car,
+            extra: plan.output.extra || args.extra,
+            chest: args.chest.unwrap_or(plan.chest),
+        };
+
+        let mut ingredients = plan
+            .ingredients()
+            .map_err(|e| format!("Invalid ingredient in plan: {e}"))?;
+        ingredients.extend(args.ingredients);
+        let ingredients =
+            merge_ingredients(ingredients).map_err(|e| format!("Invalid ingredient: {e}"))?;
+
+        let settings = Settings {
+            selection,
+            pure: plan.train.pure || args.pure,
+            filters: plan.output.filters || args.filters,
+            names: plan.output.names || args.names,
+            explain: plan.output.explain || args.explain,
+            blueprint: plan.output.blueprint || args.blueprint,
+            schedule: args.schedule.or(plan.output.schedule),
+            interrupts: plan.output.interrupts || args.interrupts,
+            station: plan.output.station || args.station,
+            combinators: plan.output.combinators || args.combinators,
+            dispatcher: args.dispatcher.or(plan.output.dispatcher),
+            train_limit: args.train_limit.or(plan.train.train_limit).unwrap_or(1),
+            sides: args.sides.or(plan.train.sides).unwrap_or(1),
+            locomotives: args.locomotives.or(plan.train.locomotives).unwrap_or(1),
+            exact: plan.output.exact || args.exact,
+            verbosity: match (args.verbosity, args.full, args.compact) {
+                (Some(verbosity), _, _) => verbosity,
+                (None, true, _) => Verbosity::Full,
+                (None, _, true) => Verbosity::Compact,
+                (None, false, false) => plan.output.verbosity,
+            },
+            format: args.format.unwrap_or(plan.output.format),
+            compare: args.compare,
+            target_error: args.target.unwrap_or(5.0) / 100.0,
+            save: args.save,
+            ingredients,
+        };
+
+        if args.target.is_some() && settings.compare.is_none() {
+            return Err(
+                "--target only applies when comparing train lengths with --compare.".into(),
+            );
+        }
+
+        if args.locomotives.is_some() && !settings.prints_blueprints() {
+            return Err(
+                "--locomotives only applies to blueprints with --blueprint, --station, --combinators or --dispatcher."
+                    .into(),
+            );
+        }
+
+        if settings.schedule.is_some() && !settings.blueprint {
+            return Err(
+                "--schedule only applies to train blueprints. Pass --blueprint too.".into(),
+            );
+        }
+
+        if settings.interrupts && settings.schedule.is_none() {
+            return Err(
+                "--interrupts only applies to train schedules. Pass --schedule too.".into(),
+            );
+        }
+
+        if args.train_limit.is_some() && settings.dispatcher.is_none() {
+            return Err("--train-limit only applies to station settings with --dispatcher.".into());
+        }
+
+        if args.sides.is_some() && !settings.station {
+            return Err("--sides only applies to station blueprints with --station.".into());
+        }
+
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Whether any blueprint is to be printed instead of the usual output.
+    pub fn prints_blueprints(&self) -> bool {
+        self.blueprint || self.station || self.combinators || self.dispatcher.is_some()
+    }
+
+    /// Check that the settings make sense together.
+    pub fn validate(&self) -> Result<(), String> {
+        let selection = &self.selection;
+
+        if let Some(cars) = selection
+            .cars
+            .iter()
+            .find(|cars| **cars == 0 || **cars > MAX_CARS)
+        {
+            return Err(format!(
+                "Trains can have 1 to {MAX_CARS} cargo wagons, got {cars}."
+            ));
+        }
+
+        if self.locomotives == 0 || self.locomotives > MAX_CARS {
+            return Err(format!(
+                "Trains can have 1 to {MAX_CARS} locomotives, got {}.",
+                self.locomotives
+            ));
+        }
+
+        if self.train_limit == 0 || self.train_limit > MAX_TRAIN_LIMIT {
+            return Err(format!(
+                "Stations can allow 1 to {MAX_TRAIN_LIMIT} trains at once, got {}.",
+                self.train_limit
+            ));
+        }
+
+        if self.target_error.is_nan() || self.target_error < 0.0 {
+            return Err("--target must be a percentage of 0 or more.".into());
+        }
+
+        if self.filters && matches!(self.format, Format::Csv | Format::Markdown) {
+            return Err("--filters cannot be combined with table output, as filter grids are not tables. Use --format text or json.".into());
+        }
+
+        if self.names && self.format != Format::Text {
+            return Err("--names only applies to text output.".into());
+        }
+
+        if self.explain && self.format != Format::Text {
+            return Err("--explain only applies to text output.".into());
+        }
+
+        if self.prints_blueprints() && self.explain {
+            return Err("--explain cannot be combined with --blueprint, --station, --combinators or --dispatcher, as they replace the usual output.".into());
+        }
+
+        if self.prints_blueprints() && self.format != Format::Text {
+            return Err("--blueprint, --station, --combinators and --dispatcher cannot be combined with --format, as they replace the usual output.".into());
+        }
+
+        if self.compare.is_some() {
+            if self.prints_blueprints() {
+                return Err("--compare cannot be combined with --blueprint, --station, --combinators or --dispatcher, as they all replace the usual output.".into());
+            }
+            if self.filters || self.names || self.explain {
+                return Err(
+                    "--compare cannot be combined with --filters, --names or --explain, as it replaces the usual output."
+                        .into(),
+                );
+            }
+            return Ok(());
+        }
+
+        if selection.cars.is_empty() {
+            return Err("No train length was given. Pass the number of cargo wagons with --cars (e.g. \"--cars 4\"), or compare train lengths with --compare.".into());
+        }
+
+        if let Some(cars) = selection.cars.iter().find(|cars| **cars > 1) {
+            if !selection.same_car && !selection.different_car {
+                return Err(format!("A {cars}-car train needs its wagon filtering to be chosen. Pass --sc, --dc or both."));
+            }
+        }
+
+        if self.pure && !selection.different_car {
+            return Err("--pure only applies to DC filtering. Pass --dc too.".into());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translates_legacy_arguments() {
+        for (legacy, translated) in [
+            ("4-car", "--cars=4"),
+            ("12-car", "--cars=12"),
+            ("help", "--help"),
+            ("sc", "--sc"),
+            ("dc", "--dc"),
+            ("extra", "--extra"),
+            ("filters", "--filters"),
+            ("pure", "--pure"),
+            ("exact", "--exact"),
+            ("compare", "--compare"),
+            ("compare=2-6", "--compare=2-6"),
+            ("target=1/s", "--target=1/s"),
+            ("chest=iron", "--chest=iron"),
+        ] {
+            assert_eq!(translate_legacy_arg(legacy.to_string()), translated);
+        }
+    }
+
+    #[test]
+    fn leaves_other_arguments_alone() {
+        for arg in [
+            "--cars=4",
+            "-car",
+            "x-car",
+            "4-cars",
+            "iron-plate",
+            "names",
+            "sc=1",
+            "--compare",
+            "tui",
+        ] {
+            assert_eq!(translate_legacy_arg(arg.to_string()), arg);
+        }
+    }
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(parse_range("2-6"), Ok(2..=6));
+        assert_eq!(parse_range(" 1 - 1 "), Ok(1..=1));
+        assert_eq!(parse_range("1-100"), Ok(1..=MAX_CARS));
+    }
+
+    #[test]
+    fn rejects_invalid_ranges() {
+        for range in ["", "4", "2-", "-6", "0-4", "6-2", "a-b", "2-6-8", "-1-4"] {
+            assert!(parse_range(range).is_err(), "{range:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_ranges_past_the_wagon_cap() {
+        let error = parse_range("1-101").unwrap_err();
+        assert!(error.contains("up to 100 cargo wagons"), "{error}");
+        assert!(parse_range("2-4294967295").is_err());
+        assert!(parse_range("2-4294967296").is_err());
+    }
+
+    /// The settings of the given arguments, without a plan.
+    fn settings(args: &[&str]) -> Result<Settings, String> {
+        let args = Args::try_parse_from(std::iter::once("factrain").chain(args.iter().copied()))
+            .map_err(|e| e.to_string())?;
+        Settings::new(args, &Plan::default())
+    }
+
+    #[test]
+    fn merges_options_into_the_plans() {
+        let mut plan = Plan::default();
+        plan.train.cars = vec![2];
+        plan.train.same_car = true;
+        plan.chest = Chest::Wooden;
+        let args = Args::try_parse_from(["factrain", "--dc", "--chest", "iron", "iron plate=100"])
+            .unwrap();
+        let settings = Settings::new(args, &plan).unwrap();
+        assert_eq!(settings.selection.cars, vec![2]);
+        assert!(settings.selection.same_car && settings.selection.different_car);
+        assert_eq!(settings.selection.chest, Chest::Iron);
+        assert_eq!(settings.verbosity, Verbosity::Normal);
+        assert_eq!(settings.target_error, 0.05);
+    }
+
+    #[test]
+    fn chooses_the_verbosity() {
+        let verbosity = |args: &[&str]| {
+            settings(&[args, &["--cars", "1", "iron plate=100"]].concat()).map(|s| s.verbosity)
+        };
+        assert_eq!(verbosity(&[]), Ok(Verbosity::Normal));
+        assert_eq!(verbosity(&["-f"]), Ok(Verbosity::Full));
+        assert_eq!(verbosity(&["-q"]), Ok(Verbosity::Compact));
+        assert_eq!(
+            verbosity(&["--verbosity", "compact"]),
+            Ok(Verbosity::Compact)
+        );
+        for conflicting in [
+            &["-f", "-q"][..],
+            &["-f", "--verbosity", "full"],
+            &["-q", "--verbosity", "full"],
+        ] {
+            assert!(verbosity(conflicting).is_err(), "{conflicting:?}");
+        }
+
+        let mut plan = Plan::default();
+        plan.train.cars = vec![1];
+        plan.output.verbosity = Verbosity::Full;
+        let args = Args::try_parse_from(["factrain", "iron plate=100"]).unwrap();
+        assert_eq!(
+            Settings::new(args, &plan).unwrap().verbosity,
+            Verbosity::Full
+        );
+        let args = Args::try_parse_from(["factrain", "-q", "iron plate=100"]).unwrap();
+        assert_eq!(
+            Settings::new(args, &plan).unwrap().verbosity,
+            Verbosity::Compact
+        );
+    }
+
+    #[test]
+    fn rejects_conflicting_options() {
+        for (args, expected) in [
+            (&["iron plate=100"][..], "No train length was given"),
+            (
+                &["--cars", "4", "iron plate=100"],
+                "needs its wagon filtering",
+            ),
+            (
+                &["--cars", "4", "--sc", "--pure", "iron plate=100"],
+                "--pure only applies",
+            ),
+            (
+                &["--cars", "1", "--target", "2", "iron plate=100"],
+                "--target only applies",
+            ),
+            (
+                &["--target=-1", "--compare=1-8", "iron plate=100"],
+                "--target must be",
+            ),
+            (
+                &["--cars", "1", "--sides", "2", "iron plate=100"],
+                "--sides only applies",
+            ),
+            (
+                &["--cars", "1", "--locomotives", "2", "iron plate=100"],
+                "--locomotives only applies",
+            ),
+            (
+                &["--cars", "1", "--train-limit", "2", "iron plate=100"],
+                "--train-limit only applies",
+            ),
+            (
+                &[
+                    "--cars",
+                    "1",
+                    "--blueprint",
+                    "--interrupts",
+                    "iron plate=100",
+                ],
+                "--interrupts only applies",
+            ),
+            (
+                &["--cars", "1", "--schedule", "iron plate=100"],
+                "--schedule only applies",
+            ),
+            (
+                &[
+                    "--cars",
+                    "1",
+                    "--names",
+                    "--format",
+                    "json",
+                    "iron plate=100",
+                ],
+                "--names only applies",
+            ),
+            (
+                &[
+                    "--cars",
+                    "1",
+                    "--filters",
+                    "--format",
+                    "csv",
+                    "iron plate=100",
+                ],
+                "--filters cannot",
+            ),
+            (
+                &["--cars", "1", "--station", "--explain", "iron plate=100"],
+                "--explain cannot",
+            ),
+            (
+                &[
+                    "--cars",
+                    "1",
+                    "--blueprint",
+                    "--format",
+                    "json",
+                    "iron plate=100",
+                ],
+                "cannot be combined with --format",
+            ),
+            (
+                &["--compare=1-8", "--blueprint", "iron plate=100"],
+                "--compare cannot",
+            ),
+            (
+                &["--compare=1-8", "--names", "iron plate=100"],
+                "--compare cannot",
+            ),
+        ] {
+            let error = settings(args).unwrap_err();
+            assert!(error.contains(expected), "{args:?}: {error}");
+        }
+        assert!(settings(&["--compare=1-8", "iron plate=100"]).is_ok());
+        assert!(settings(&["--cars", "1", "iron plate=100"]).is_ok());
+    }
+
+    #[test]
+    fn caps_the_train_length() {
+        assert!(settings(&["--cars", "100", "--dc", "iron plate=100"]).is_ok());
+        for cars in ["0", "101", "200000000"] {
+            assert!(settings(&["--cars", cars, "--dc", "iron plate=100"]).is_err());
+        }
+        assert!(settings(&[
+            "--cars",
+            "4",
+            "--blueprint",
+            "--locomotives",
+            "101",
+            "iron plate=100"
+        ])
+        .is_err());
+
+        let mut plan = Plan::default();
+        plan.train.cars = vec![4, 200_000_000];
+        plan.train.different_car = true;
+        let args = Args::try_parse_from(["factrain", "iron plate=100"]).unwrap();
+        let error = Settings::new(args, &plan).unwrap_err();
+        assert!(error.contains("1 to 100 cargo wagons"), "{error}");
+    }
+
+    #[test]
+    fn caps_the_train_limit() {
+        let limit = |limit: &str| {
+            settings(&[
+                "--cars",
+                "4",
+                "--dc",
+                "--dispatcher",
+                "ltn",
+                "--train-limit",
+                limit,
+                "iron plate=100",
+            ])
+        };
+        assert_eq!(limit("2147483647").unwrap().train_limit, MAX_TRAIN_LIMIT);
+        for train_limit in ["0", "2147483648"] {
+            assert!(limit(train_limit).is_err());
+        }
+
+        let mut plan = Plan::default();
+        plan.train.cars = vec![4];
+        plan.train.different_car = true;
+        plan.train.train_limit = Some(u32::MAX);
+        plan.output.dispatcher = Some(Dispatcher::Ltn);
+        let args = Args::try_parse_from(["factrain", "iron plate=100"]).unwrap();
+        let error = Settings::new(args, &plan).unwrap_err();
+        assert!(error.contains("1 to 2147483647 trains"), "{error}");
+    }
+}
@@ -0,0 +1,385 @@
+use crate::{
+    blueprint::{self, ImportedBlueprint, ImportedEntity},
+    calculate_ratios, data,
+    rational::Rational,
+    report::Chest,
+    wagon::WAGON_SLOTS,
+    Ingredient, IngredientRatio,
+};
+
+/// The items found in one kind of entity of an audited blueprint, and the ratio they deliver.
+#[derive(Debug, Clone)]
+pub struct AuditSection {
+    /// The title of the section, as printed in its banner (e.g. "WAGON FILTERS").
+    pub title: String,
+    /// The unit the counts are in (e.g. "stacks").
+    pub unit: &'static str,
+    /// The number of slots (or inserters) filtered to each item, in the same order as [Audit::items].
+    pub counts: Vec<u32>,
+    /// The ratio of items each count delivers, in the same order as [Audit::items].
+    pub ratios: Vec<f64>,
+}
+
+/// The ratio a blueprint delivers, read from its cargo wagon filters, inserter filters and chest limits.
+#[derive(Debug, Clone)]
+pub struct Audit {
+    /// The name of the blueprint.
+    pub label: String,
+    /// Every item found in the blueprint or the target plan, with its stack size in the plan, or its catalog stack size if it is not in the plan. Amounts are not meaningful.
+    pub items: Vec<Ingredient>,
+    /// The sections found in the blueprint. Sections with nothing in them are left out.
+    pub sections: Vec<AuditSection>,
+    /// The ratio of each item in the target plan (0 for items not in it), if a target plan was given.
+    pub target: Option<Vec<f64>>,
+}
+
+impl Audit {
+    /// Audit a decoded blueprint against an optional target plan.
+    ///
+    /// # Parameters
+    /// * `blueprint` - The blueprint to audit.
+    /// * `target` - The ingredients the blueprint is meant to deliver, if known.
+    ///
+    /// # Returns
+    /// The audit as [Ok]\(audit), or [Err]\(message) if an item in the blueprint has no known stack size.
+    pub fn new(blueprint: &ImportedBlueprint, target: &[IngredientRatio]) -> Result<Self, String> {
+        let mut audit = Audit {
+            label: blueprint
+                .label
+                .clone()
+                .unwrap_or("unnamed blueprint".into()),
+            items: Vec::new(),
+            sections: Vec::new(),
+            target: None,
+        };
+
+        // Cargo wagon filters, in stacks.
+        let wagons: Vec<&ImportedEntity> = blueprint
+            .entities
+            .iter()
+            .filter(|entity| entity.name.contains("cargo-wagon"))
+            .collect();
+        let mut wagon_counts = Vec::new();
+        let mut filtered_slots = 0;
+        for wagon in &wagons {
+            for filter in wagon
+                .inventory
+                .iter()
+                .flat_map(|inventory| &inventory.filters)
+            {
+                let index = audit.item_index(filter.name.as_str(), target)?;
+                add_count(&mut wagon_counts, index, 1);
+                filtered_slots += 1;
+            }
+        }
+        let wagon_slots = WAGON_SLOTS * wagons.len() as u32;
+        audit.push_section(
+            format!(
+                "WAGON FILTERS ({} wagons, {} of {} slots filtered)",
+                wagons.len(),
+                filtered_slots,
+                wagon_slots
+            ),
+            "stacks",
+            wagon_counts,
+            true,
+        );
+
+        // Inserter filters, in inserters. Each inserter moves items at the same rate, whatever their stack size.
+        let inserters: Vec<&ImportedEntity> = blueprint
+            .entities
+            .iter()
+            .filter(|entity| entity.name.contains("inserter") && !entity.filters.is_empty())
+            .collect();
+        let mut inserter_counts = Vec::new();
+        for inserter in &inserters {
+            let index = audit.item_index(inserter.filters[0].name.as_str(), target)?;
+            add_count(&mut inserter_counts, index, 1);
+        }
+        audit.push_section(
+            format!("INSERTER FILTERS ({} inserters)", inserters.len()),
+            "inserters",
+            inserter_counts,
+            false,
+        );
+
+        // Chest limits, in stacks. A chest holds the item of the filter inserter next to it.
+        let mut chest_counts = Vec::new();
+        let mut chest_count = 0;
+        for entity in &blueprint.entities {
+            let Some(chest) = Chest::from_entity_name(entity.name.as_str()) else {
+                continue;
+            };
+            let Some(inserter) = inserters
+                .iter()
+                .find(|inserter| is_adjacent(inserter, entity))
+            else {
+                continue;
+            };
+
+            let index = audit.item_index(inserter.filters[0].name.as_str(), target)?;
+            let slots = entity.bar.unwrap_or(chest.slots()).min(chest.slots());
+            add_count(&mut chest_counts, index, slots);
+            chest_count += 1;
+        }
+        audit.push_section(
+            format!("CHEST LIMITS ({} chests)", chest_count),
+            "stacks",
+            chest_counts,
+            true,
+        );
+
+        // Target ratios, matched to the blueprint's items by prototype name.
+        if !target.is_empty() {
+            for item in target {
+                audit.item_index(
+                    blueprint::item_name(&item.ingredient, false).as_str(),
+                    target,
+                )?;
+            }
+            audit.target = Some(
+                audit
+                    .items
+                    .iter()
+                    .map(|item| {
+                        target
+                            .iter()
+                            .filter(|target| {
+                                blueprint::item_name(&target.ingredient, false)
+                                    == blueprint::item_name(item, false)
+                            })
+                            .map(|target| target.ratio)
+                            .sum()
+                    })
+                    .collect(),
+            );
+        }
+
+        // Items only found later (or in the target) deliver none of the earlier sections.
+        let item_count = audit.items.len();
+        for section in &mut audit.sections {
+            section.counts.resize(item_count, 0);
+            section.ratios.resize(item_count, 0.0);
+        }
+
+        Ok(audit)
+    }
+
+    /// The index of the item with the given prototype name, adding it if it has not been found yet. Items in the target plan are added as the plan gives them, so that its stack sizes and Factorio 2.0 names are used; other items are added with their catalog stack size.
+    fn item_index(
+        &mut self,
+        prototype_name: &str,
+        target: &[IngredientRatio],
+    ) -> Result<usize, String> {
+        let planned = target
+            .iter()
+            .map(|item| &item.ingredient)
+            .find(|ingredient| {
+                [false, true].into_iter().any(|factorio_2| {
+                    blueprint::item_name(ingredient, factorio_2) == prototype_name
+                })
+            });
+        let name = match planned {
+            Some(ingredient) => ingredient.name.clone(),
+            None => data::fetch_item_localized_name(prototype_name),
+        };
+        if let Some(index) = self.items.iter().position(|item| item.name == name) {
+            return Ok(index);
+        }
+
+        let stack_size = match planned {
+            Some(ingredient) => ingredient.stack_size,
+            None => data::fetch_item_stack_size(name.as_str()).ok_or(format!(
+                "\"{name}\" ({prototype_name}) has no known stack size"
+            ))?,
+        };
+        self.items.push(Ingredient {
+            name,
+            amount: Rational::ZERO,
+            stack_size,
+            rate: false,
+        });
+        Ok(self.items.len() - 1)
+    }
+
+    /// Add a section to the audit if anything was found for it, calculating the ratio its counts deliver.
+    ///
+    /// # Parameters
+    /// * `title` - The title of the section.
+    /// * `unit` - The unit the counts are in.
+    /// * `counts` - The count of each item found so far.
+    /// * `stacks` - Whether each count is a full stack of its item, rather than an equal share of items.
+    fn push_section(&mut self, title: String, unit: &'static str, counts: Vec<u32>, stacks: bool) {
+        if counts.iter().all(|count| *count == 0) {
+            return;
+        }
+
+        let delivered: Vec<Ingredient> = counts
+            .iter()
+            .zip(&self.items)
+            .map(|(count, item)| Ingredient {
+                amount: Rational::from_integer(if stacks {
+                    *count as i128 * item.stack_size as i128
+                } else {
+                    *count as i128
+                }),
+                ..item.clone()
+            })
+            .collect();
+        let ratios = calculate_ratios(&delivered, false)
+            .unwrap_or_default()
+            .iter()
+            .map(|item| item.ratio)
+            .collect();
+
+        self.sections.push(AuditSection {
+            title,
+            unit,
+            counts,
+            ratios,
+        });
+    }
+
+    /// Print each section of the audit, with the ratio of each item and its deviation from the target, if any.
+    pub fn print(&self) {
+        println!("{:=^80}", format!(" AUDIT ({}) ", self.label));
+
+        if self.sections.is_empty() {
+            println!("No filtered cargo wagons, filter inserters or chests were found.");
+            return;
+        }
+
+        for section in &self.sections {
+            println!("{:-^80}", format!(" {} ", section.title));
+
+            let mut max_error: f64 = 0.0;
+            for (index, item) in self.items.iter().enumerate() {
+                let ratio = section.ratios[index];
+                let count = format!("{} {}", section.counts[index], section.unit);
+
+                match &self.target {
+                    Some(target) => {
+                        let target = target[index];
+                        let deviation = ratio - target;
+                        let error = if target > 0.0 {
+                            deviation / target
+                        } else if ratio > 0.0 {
+                            f64::INFINITY
+                        } else {
+                            0.0
+                        };
+                        max_error = max_error.max(error.abs());
+                        println!(
+                            "{:>40} ---- {}, {:.2}% of {:.2}% ({:+.2} pts, {:+.1}%)",
+                            item.name,
+                            count,
+                            ratio * 100.0,
+                            target * 100.0,
+                            deviation * 100.0,
+                            error * 100.0
+                        );
+                    }
+                    None => println!("{:>40} ---- {}, {:.2}%", item.name, count, ratio * 100.0),
+                }
+            }
+
+            if self.target.is_some() {
+                println!("{:>40} ---- {:.2}%", "MAX RATIO ERROR", max_error * 100.0);
+            }
+        }
+    }
+}
+
+/// Add `count` to the count at `index`, growing the counts as needed.
+fn add_count(counts: &mut Vec<u32>, index: usize, count: u32) {
+    if counts.len() <= index {
+        counts.resize(index + 1, 0);
+    }
+    counts[index] += count;
+}
+
+/// Whether two one-tile entities are next to each other, horizontally or vertically.
+fn is_adjacent(a: &ImportedEntity, b: &ImportedEntity) -> bool {
+    let dx = (a.position.x - b.position.x).abs();
+    let dy = (a.position.y - b.position.y).abs();
+    (dx - 1.0).abs() < 0.01 && dy < 0.01 || dx < 0.01 && (dy - 1.0).abs() < 0.01
+}
+
+/// Decode a blueprint string and print an audit of each blueprint in it.
+///
+/// # Parameters
+/// * `string` - The blueprint string, as exported from the game.
+/// * `target` - The ingredients the blueprints are meant to deliver, if known.
+///
+/// # Returns
+/// [Ok] if every blueprint was audited, or [Err]\(message) if the string is invalid or holds unknown items.
+pub fn print_audit(string: &str, target: &[IngredientRatio]) -> Result<(), String> {
+    let blueprints = blueprint::decode(string)?;
+    if blueprints.is_empty() {
+        return Err("The blueprint string holds no blueprints.".into());
+    }
+
+    for blueprint in &blueprints {
+        Audit::new(blueprint, target)?.print();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::Selection;
+    use crate::wagon;
+
+    /// Audit the train blueprint planned for the ingredients against them.
+    fn audit_train(ingredients: &[Ingredient], factorio_2: bool) -> Audit {
+        let item_ratios = calculate_ratios(ingredients, false).unwrap();
+        let selection = Selection {
+            cars: vec![1],
+            same_car: true,
+            different_car: false,
+            extra: false,
+            chest: Chest::Steel,
+        };
+        let train = &wagon::plan_trains(&item_ratios, &selection, false)[0];
+        let string = blueprint::train_blueprint(&item_ratios, train, 1, factorio_2)
+            .encode()
+            .unwrap();
+        let decoded = blueprint::decode(string.as_str()).unwrap();
+        Audit::new(&decoded[0], &item_ratios).unwrap()
+    }
+
+    #[test]
+    fn uses_the_plans_stack_sizes() {
+        let audit = audit_train(
+            &[
+                Ingredient::new("iron plate", Rational::from_integer(300), 50).unwrap(),
+                Ingredient::new("tungsten plate", Rational::from_integer(100), 50).unwrap(),
+            ],
+            false,
+        );
+
+        let stack_sizes: Vec<u32> = audit.items.iter().map(|item| item.stack_size).collect();
+        assert_eq!(stack_sizes, vec![50, 50]);
+        let wagons = &audit.sections[0];
+        assert_eq!(wagons.counts, vec![30, 10]);
+        assert_eq!(wagons.ratios, audit.target.clone().unwrap());
+    }
+
+    #[test]
+    fn matches_factorio_2_names_to_the_plan() {
+        let audit = audit_train(
+            &[
+                Ingredient::new("empty barrel", Rational::from_integer(200), 10).unwrap(),
+                Ingredient::new("iron plate", Rational::from_integer(2000), 100).unwrap(),
+            ],
+            true,
+        );
+
+        let names: Vec<&str> = audit.items.iter().map(|item| item.name.as_str()).collect();
+        assert_eq!(names, vec!["empty barrel", "iron plate"]);
+        assert_eq!(audit.items[0].stack_size, 10);
+        assert_eq!(audit.sections[0].counts, vec![20, 20]);
+    }
+}
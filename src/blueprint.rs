@@ -0,0 +1,1205 @@
+use std::io::{Read, Write};
+
+use base64::Engine;
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    allocation, data,
+    report::{chests, Chest},
+    schedule::{self, LoadWait, TrainSchedule},
+    wagon::{self, TrainFilters, WAGON_INSERTERS},
+    Ingredient, IngredientRatio,
+};
+
+/// The Factorio version blueprints are written for (1.1.110), packed as four 16-bit parts (major, minor, patch, build) as the game expects.
+const FACTORIO_VERSION: u64 = (1 << 48) | (1 << 32) | (110 << 16);
+
+/// The Factorio version blueprints using 2.0 features (such as schedule interrupts) are written for (2.0.28).
+pub const FACTORIO_2_VERSION: u64 = (2 << 48) | (28 << 16);
+
+/// The version prefix of a blueprint string, before its base64-encoded data.
+const BLUEPRINT_STRING_VERSION: char = '0';
+
+/// The distance in tiles between the fronts of two coupled pieces of rolling stock. Each is six tiles long, with a one tile gap between them.
+const ROLLING_STOCK_SPACING: f64 = 7.0;
+
+/// The length in tiles of a locomotive or cargo wagon.
+const ROLLING_STOCK_LENGTH: f64 = 6.0;
+
+/// The number of signals a constant combinator holds.
+const CONSTANT_COMBINATOR_SLOTS: usize = 20;
+
+/// The direction of an entity facing north.
+const NORTH: u8 = 0;
+
+/// The direction of an entity facing east.
+const EAST: u8 = 2;
+
+/// The direction of an entity facing west.
+const WEST: u8 = 6;
+
+/// A blueprint string as the game imports it, wrapping a single blueprint.
+#[derive(Debug, Clone, Serialize)]
+struct BlueprintString<'a> {
+    /// The blueprint.
+    blueprint: &'a Blueprint,
+}
+
+/// A Factorio blueprint.
+#[derive(Debug, Clone, Serialize)]
+pub struct Blueprint {
+    /// The item the blueprint is, always "blueprint".
+    item: &'static str,
+    /// The name of the blueprint shown in game.
+    pub label: String,
+    /// The icons of the blueprint shown in game.
+    pub icons: Vec<Icon>,
+    /// The entities of the blueprint.
+    pub entities: Vec<Entity>,
+    /// The schedules of the trains in the blueprint.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub schedules: Vec<TrainSchedule>,
+    /// The game version the blueprint was written for.
+    pub version: u64,
+}
+
+/// An icon of a [Blueprint].
+#[derive(Debug, Clone, Serialize)]
+pub struct Icon {
+    /// The position of the icon, starting at 1.
+    pub index: u32,
+    /// The signal the icon shows.
+    pub signal: Signal,
+}
+
+/// A signal (e.g. an item) shown as an icon or sent over a circuit network.
+#[derive(Debug, Clone, Serialize)]
+pub struct Signal {
+    /// The type of signal, such as "item" or "virtual".
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// The prototype name of the signal (e.g. "iron-plate").
+    pub name: String,
+}
+
+/// An entity placed by a [Blueprint].
+#[derive(Debug, Clone, Serialize)]
+pub struct Entity {
+    /// The number of the entity within the blueprint, starting at 1.
+    pub entity_number: u32,
+    /// The prototype name of the entity (e.g. "cargo-wagon").
+    pub name: &'static str,
+    /// The position of the centre of the entity, in tiles.
+    pub position: Position,
+    /// The direction the entity faces (0 north, 2 east, 4 south or 6 west), if it can be rotated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direction: Option<u8>,
+    /// The direction rolling stock faces, as a fraction of a full turn clockwise from north.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<f64>,
+    /// The name of a train stop.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub station: Option<String>,
+    /// The item filters of a filter inserter.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filters: Option<Vec<Filter>>,
+    /// The inventory filters of the entity, if it has any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inventory: Option<Inventory>,
+    /// The number of usable slots of a container, if limited (the "red X" inventory bar).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bar: Option<u32>,
+    /// The signals of a constant combinator.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub control_behavior: Option<ControlBehavior>,
+}
+
+/// The circuit network settings of an [Entity].
+#[derive(Debug, Clone, Serialize)]
+pub struct ControlBehavior {
+    /// The signals a constant combinator outputs.
+    pub filters: Vec<SignalFilter>,
+}
+
+/// A signal output by a constant combinator.
+#[derive(Debug, Clone, Serialize)]
+pub struct SignalFilter {
+    /// The slot of the combinator holding the signal, starting at 1.
+    pub index: u32,
+    /// The signal output.
+    pub signal: Signal,
+    /// The value of the signal.
+    pub count: i32,
+}
+
+impl Entity {
+    /// Create an entity with nothing but its name and position set.
+    pub fn new(name: &'static str, position: Position) -> Self {
+        Entity {
+            entity_number: 0,
+            name,
+            position,
+            direction: None,
+            orientation: None,
+            station: None,
+            filters: None,
+            inventory: None,
+            bar: None,
+            control_behavior: None,
+        }
+    }
+}
+
+/// A position within a [Blueprint], in tiles.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Position {
+    /// The distance east.
+    pub x: f64,
+    /// The distance south.
+    pub y: f64,
+}
+
+/// The filtered inventory of an [Entity].
+#[derive(Debug, Clone, Serialize)]
+pub struct Inventory {
+    /// The filter of each filtered slot. Unfiltered slots are left out.
+    pub filters: Vec<Filter>,
+}
+
+/// The filter of one inventory slot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Filter {
+    /// The slot filtered, starting at 1.
+    pub index: u32,
+    /// The prototype name of the item the slot is filtered to.
+    pub name: String,
+}
+
+impl Blueprint {
+    /// Create an empty blueprint.
+    ///
+    /// # Parameters
+    /// * `label` - The name of the blueprint shown in game.
+    /// * `icons` - The prototype names of the items shown as the blueprint's icons, in order.
+    pub fn new(label: String, icons: &[&str]) -> Self {
+        Blueprint {
+            item: "blueprint",
+            label,
+            icons: icons
+                .iter()
+                .zip(1..)
+                .map(|(name, index)| Icon {
+                    index,
+                    signal: Signal {
+                        kind: "item",
+                        name: name.to_string(),
+                    },
+                })
+                .collect(),
+            entities: Vec::new(),
+            schedules: Vec::new(),
+            version: FACTORIO_VERSION,
+        }
+    }
+
+    /// Add an entity to the blueprint, numbering it after the entities already added.
+    pub fn push_entity(&mut self, entity: Entity) {
+        self.entities.push(Entity {
+            entity_number: self.entities.len() as u32 + 1,
+            ..entity
+        });
+    }
+
+    /// Add a row of constant combinators outputting the given signals, starting at `position` and continuing east, with as many combinators as are needed to hold every signal.
+    pub fn push_combinators(&mut self, position: Position, signals: &[(Signal, i32)]) {
+        for (offset, chunk) in signals.chunks(CONSTANT_COMBINATOR_SLOTS).enumerate() {
+            self.push_entity(Entity {
+                direction: Some(NORTH),
+                control_behavior: Some(ControlBehavior {
+                    filters: chunk
+                        .iter()
+                        .zip(1..)
+                        .map(|((signal, count), index)| SignalFilter {
+                            index,
+                            signal: signal.clone(),
+                            count: *count,
+                        })
+                        .collect(),
+                }),
+                ..Entity::new(
+                    "constant-combinator",
+                    Position {
+                        x: position.x + offset as f64,
+                        y: position.y,
+                    },
+                )
+            });
+        }
+    }
+
+    /// Encode the blueprint as a string the game can import: its JSON, zlib-compressed and base64-encoded, after a version prefix.
+    ///
+    /// # Returns
+    /// The blueprint string as [Ok]\(string), or [Err]\(message) if it could not be encoded.
+    pub fn encode(&self) -> Result<String, String> {
+        let json = serde_json::to_vec(&BlueprintString { blueprint: self })
+            .map_err(|e| format!("Could not encode blueprint: {e}"))?;
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        let compressed = encoder
+            .write_all(json.as_slice())
+            .and_then(|()| encoder.finish())
+            .map_err(|e| format!("Could not compress blueprint: {e}"))?;
+
+        Ok(format!(
+            "{}{}",
+            BLUEPRINT_STRING_VERSION,
+            base64::engine::general_purpose::STANDARD.encode(compressed)
+        ))
+    }
+}
+
+/// A blueprint string as read from the game, holding either a single blueprint or a book of them.
+#[derive(Debug, Clone, Deserialize)]
+struct ImportedString {
+    /// The blueprint, if the string holds a single blueprint.
+    blueprint: Option<ImportedBlueprint>,
+    /// The blueprint book, if the string holds a book.
+    blueprint_book: Option<ImportedBook>,
+}
+
+/// A blueprint book as read from the game.
+#[derive(Debug, Clone, Deserialize)]
+struct ImportedBook {
+    /// The blueprints in the book. Anything else the book holds is ignored.
+    #[serde(default)]
+    blueprints: Vec<ImportedString>,
+}
+
+/// A blueprint as read from the game, keeping only what factrain understands.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportedBlueprint {
+    /// The name of the blueprint shown in game.
+    #[serde(default)]
+    pub label: Option<String>,
+    /// The entities of the blueprint.
+    #[serde(default)]
+    pub entities: Vec<ImportedEntity>,
+}
+
+/// An entity of an [ImportedBlueprint].
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportedEntity {
+    /// The prototype name of the entity (e.g. "cargo-wagon").
+    pub name: String,
+    /// The position of the centre of the entity, in tiles.
+    pub position: ImportedPosition,
+    /// The item filters of a filter inserter.
+    #[serde(default)]
+    pub filters: Vec<Filter>,
+    /// The inventory filters of the entity, if it has any.
+    #[serde(default)]
+    pub inventory: Option<ImportedInventory>,
+    /// The number of usable slots of a container, if limited.
+    #[serde(default)]
+    pub bar: Option<u32>,
+}
+
+/// A position within an [ImportedBlueprint], in tiles.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ImportedPosition {
+    /// The distance east.
+    pub x: f64,
+    /// The distance south.
+    pub y: f64,
+}
+
+/// The filtered inventory of an [ImportedEntity].
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportedInventory {
+    /// The filter of each filtered slot.
+    #[serde(default)]
+    pub filters: Vec<Filter>,
+}
+
+/// Decode a blueprint string exported from the game, reversing [Blueprint::encode]. Blueprint books are flattened into the blueprints they hold.
+///
+/// # Returns
+/// The blueprints as [Ok]\(blueprints), or [Err]\(message) if the string is not a valid blueprint string.
+pub fn decode(string: &str) -> Result<Vec<ImportedBlueprint>, String> {
+    let data = string
+        .trim()
+        .strip_prefix(BLUEPRINT_STRING_VERSION)
+        .ok_or("Invalid blueprint string: unknown version, expected it to start with \"0\"")?;
+
+    let compressed = base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .map_err(|e| format!("Invalid blueprint string: {e}"))?;
+
+    let mut json = Vec::new();
+    ZlibDecoder::new(compressed.as_slice())
+        .read_to_end(&mut json)
+        .map_err(|e| format!("Invalid blueprint string: {e}"))?;
+
+    let imported: ImportedString = serde_json::from_slice(json.as_slice())
+        .map_err(|e| format!("Invalid blueprint string: {e}"))?;
+
+    let mut blueprints = Vec::new();
+    let mut pending = vec![imported];
+    while let Some(imported) = pending.pop() {
+        blueprints.extend(imported.blueprint);
+        if let Some(book) = imported.blueprint_book {
+            pending.extend(book.blueprints.into_iter().rev());
+        }
+    }
+
+    Ok(blueprints)
+}
+
+/// The prototype name of an ingredient's item (e.g. "iron-plate"), in Factorio 2.0 if `factorio_2` or in Factorio 1.1 otherwise. Items not known to be in the vanilla game are assumed to be named after their lowercase name with hyphens for spaces, as most mods name their items. Items removed in Factorio 2.0 keep their 1.1 name, so check for them with [check_factorio_2] first.
+pub fn item_name(ingredient: &Ingredient, factorio_2: bool) -> String {
+    let name = match data::fetch_item_prototype_name(ingredient.name.as_str()) {
+        Some(name) => name.to_string(),
+        None => ingredient.name.replace(' ', "-"),
+    };
+    match factorio_2 {
+        true => data::fetch_item_prototype_name_2(name.as_str())
+            .map(str::to_string)
+            .unwrap_or(name),
+        false => name,
+    }
+}
+
+/// Check that every ingredient's item still exists in Factorio 2.0, so that it can be written into a blueprint for Factorio 2.0.
+///
+/// # Returns
+/// [Ok] if every item exists in Factorio 2.0, or [Err]\(message) naming the first item that was removed.
+pub fn check_factorio_2(item_ratios: &[IngredientRatio]) -> Result<(), String> {
+    for item in item_ratios {
+        let name = item_name(&item.ingredient, false);
+        if data::fetch_item_prototype_name_2(name.as_str()).is_none() {
+            return Err(format!(
+                "\"{}\" was removed in Factorio 2.0, so it cannot be used with --interrupts.",
+                item.ingredient.name
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Build a blueprint of a train, with its locomotives at the front and the inventory filters of each cargo wagon set as planned. The train faces north, and snaps onto rails when placed.
+///
+/// # Parameters
+/// * `item_ratios` - The ingredients the filters were planned for.
+/// * `train` - The planned filters of every wagon of the train.
+/// * `locomotives` - The number of locomotives at the front of the train.
+/// * `factorio_2` - Whether to name items as in Factorio 2.0.
+pub fn train_blueprint(
+    item_ratios: &[IngredientRatio],
+    train: &TrainFilters,
+    locomotives: u32,
+    factorio_2: bool,
+) -> Blueprint {
+    let item_names: Vec<String> = item_ratios
+        .iter()
+        .map(|item| item_name(&item.ingredient, factorio_2))
+        .collect();
+
+    let mut blueprint = Blueprint::new(
+        format!("{}-{} train ({})", locomotives, train.cars, train.title()),
+        &["locomotive", "cargo-wagon"],
+    );
+
+    let position = |index: u32| Position {
+        x: 1.0,
+        y: index as f64 * ROLLING_STOCK_SPACING + ROLLING_STOCK_LENGTH / 2.0,
+    };
+
+    for index in 0..locomotives {
+        blueprint.push_entity(Entity {
+            orientation: Some(0.0),
+            ..Entity::new("locomotive", position(index))
+        });
+    }
+
+    for (index, wagon) in (locomotives..).zip(&train.wagons) {
+        let filters = wagon
+            .slots
+            .iter()
+            .zip(1..)
+            .filter_map(|(slot, index)| {
+                slot.map(|item| Filter {
+                    index,
+                    name: item_names[item].clone(),
+                })
+            })
+            .collect();
+
+        blueprint.push_entity(Entity {
+            orientation: Some(0.0),
+            inventory: Some(Inventory { filters }),
+            ..Entity::new("cargo-wagon", position(index))
+        });
+    }
+
+    blueprint
+}
+
+/// Build a blueprint of an unloading station for a train, stopped facing north at its train stop. Along each side of the rails, six filter inserters beside each cargo wagon unload it into a row of chests, leaving the tiles between wagons empty. Each wagon's inserters are given to the items that wagon holds by the number of each it holds, and each chest's inventory bar limits it to the item's share of that wagon's chest slots.
+///
+/// # Parameters
+/// * `item_ratios` - The ingredients to be unloaded.
+/// * `train` - The planned filters of every wagon of the train.
+/// * `locomotives` - The number of locomotives at the front of the train.
+/// * `chest` - The type of chest to unload into.
+/// * `sides` - The number of sides of the rails to unload from (1 for the right-hand side only, or 2 for both).
+///
+/// # Returns
+/// The blueprint as [Ok]\(blueprint), or [Err]\(message) if a wagon holds more items than there are inserters beside it to unload them.
+pub fn station_blueprint(
+    item_ratios: &[IngredientRatio],
+    train: &TrainFilters,
+    locomotives: u32,
+    chest: Chest,
+    sides: u32,
+) -> Result<Blueprint, String> {
+    let item_names: Vec<String> = item_ratios
+        .iter()
+        .map(|item| item_name(&item.ingredient, false))
+        .collect();
+
+    // The filter and inventory bar of the inserters and chests beside each wagon, with the first side's before the second side's.
+    let inserters_per_wagon = WAGON_INSERTERS * sides;
+    let mut wagons: Vec<Vec<(Option<usize>, Option<u32>)>> = Vec::new();
+    for (number, wagon) in train.wagons.iter().enumerate() {
+        let held = wagon
+            .items(item_ratios)
+            .iter()
+            .filter(|items| **items > 0.0)
+            .count() as u32;
+        if held > inserters_per_wagon {
+            return Err(format!(
+                "Cannot unload wagon {} of the {} train: it holds {} items, but only {} inserters fit beside it. Add --sides 2 or use fewer ingredients.",
+                number + 1,
+                train.title(),
+                held,
+                inserters_per_wagon
+            ));
+        }
+        let inserters = wagon::fill_slots(&wagon.inserters(item_ratios, inserters_per_wagon));
+
+        // Spread each item's share of the wagon's chest slots evenly across the chests its inserters fill.
+        let stacks: Vec<f64> = (0..item_ratios.len())
+            .map(|index| wagon.count(index) as f64)
+            .collect();
+        let stacks = allocation::apportion(&stacks, chest.slots() * inserters_per_wagon);
+        let mut bars: Vec<std::vec::IntoIter<u32>> = (0..item_ratios.len())
+            .map(|index| {
+                let count = inserters
+                    .iter()
+                    .filter(|slot| **slot == Some(index))
+                    .count();
+                let slots = stacks[index].min(chest.slots() * count as u32);
+                allocation::apportion(&vec![1.0; count], slots).into_iter()
+            })
+            .collect();
+
+        wagons.push(
+            inserters
+                .iter()
+                .map(|slot| {
+                    let bar = slot
+                        .and_then(|index| bars[index].next())
+                        .map(|bar| bar.max(1))
+                        .filter(|bar| *bar < chest.slots());
+                    (*slot, bar)
+                })
+                .collect(),
+        );
+    }
+
+    let chest_count = chests(train.cars) * sides;
+    let mut blueprint = Blueprint::new(
+        format!(
+            "Unloading station ({}, {} {} chests)",
+            train.title(),
+            chest_count,
+            chest.name()
+        ),
+        &["train-stop", chest.entity_name()],
+    );
+
+    // Rails, long enough for the whole train.
+    let length = locomotives.saturating_add(train.cars) as f64 * ROLLING_STOCK_SPACING;
+    for rail in 0..(length / 2.0).ceil() as u32 {
+        blueprint.push_entity(Entity {
+            direction: Some(NORTH),
+            ..Entity::new(
+                "straight-rail",
+                Position {
+                    x: 1.0,
+                    y: rail as f64 * 2.0 + 1.0,
+                },
+            )
+        });
+    }
+
+    blueprint.push_entity(Entity {
+        direction: Some(NORTH),
+        station: Some(station_name(
+            item_ratios,
+            train.cars,
+            StationRole::Drop,
+            false,
+        )),
+        ..Entity::new("train-stop", Position { x: 3.0, y: 1.0 })
+    });
+
+    // Inserters face the wagon they take from, and drop into the chest behind them.
+    let rows = [(2.5, 3.5, WEST), (-0.5, -1.5, EAST)];
+    for (index, wagon) in (locomotives..).zip(&wagons) {
+        let front = index as f64 * ROLLING_STOCK_SPACING;
+        for (side, &(inserter_x, chest_x, direction)) in
+            rows.iter().take(sides as usize).enumerate()
+        {
+            let start = side * WAGON_INSERTERS as usize;
+            let tiles = &wagon[start..start + WAGON_INSERTERS as usize];
+            for (tile, (slot, bar)) in tiles.iter().enumerate() {
+                let y = front + tile as f64 + 0.5;
+
+                blueprint.push_entity(Entity {
+                    direction: Some(direction),
+                    filters: slot.map(|index| {
+                        vec![Filter {
+                            index: 1,
+                            name: item_names[index].clone(),
+                        }]
+                    }),
+                    ..Entity::new("stack-filter-inserter", Position { x: inserter_x, y })
+                });
+
+                blueprint.push_entity(Entity {
+                    bar: *bar,
+                    ..Entity::new(chest.entity_name(), Position { x: chest_x, y })
+                });
+            }
+        }
+    }
+
+    Ok(blueprint)
+}
+
+/// Build a blueprint of constant combinators for a train, with one combinator beside each cargo wagon outputting the number of each item the wagon is loaded with. Wired to the wagon's loading inserters, these enable each inserter while the wagon holds less than its count (e.g. "iron plate < 4000").
+///
+/// # Parameters
+/// * `item_ratios` - The ingredients the filters were planned for.
+/// * `train` - The planned filters of every wagon of the train.
+/// * `locomotives` - The number of locomotives at the front of the train, to line the combinators up with the wagons.
+pub fn combinator_blueprint(
+    item_ratios: &[IngredientRatio],
+    train: &TrainFilters,
+    locomotives: u32,
+) -> Blueprint {
+    let mut blueprint = Blueprint::new(
+        format!("Loading counts ({})", train.title()),
+        &["constant-combinator"],
+    );
+
+    for (index, wagon) in (locomotives..).zip(&train.wagons) {
+        let signals = item_signals(item_ratios, false, |item| wagon.count(item));
+        blueprint.push_combinators(
+            Position {
+                x: 3.5,
+                y: index as f64 * ROLLING_STOCK_SPACING + ROLLING_STOCK_LENGTH / 2.0,
+            },
+            &signals,
+        );
+    }
+
+    blueprint
+}
+
+/// The item signal of each ingredient a number of stacks is given for, counting the items in those stacks. Ingredients with no stacks are left out.
+///
+/// # Parameters
+/// * `item_ratios` - The ingredients to make signals of.
+/// * `factorio_2` - Whether to name items as in Factorio 2.0.
+/// * `stacks` - The number of stacks of the ingredient at an index.
+pub fn item_signals(
+    item_ratios: &[IngredientRatio],
+    factorio_2: bool,
+    stacks: impl Fn(usize) -> u32,
+) -> Vec<(Signal, i32)> {
+    item_ratios
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| stacks(*index) > 0)
+        .map(|(index, item)| {
+            (
+                Signal {
+                    kind: "item",
+                    name: item_name(&item.ingredient, factorio_2),
+                },
+                // Circuit signals are 32-bit, so larger counts are held at the largest signal.
+                i32::try_from(stacks(index) as u64 * item.ingredient.stack_size as u64)
+                    .unwrap_or(i32::MAX),
+            )
+        })
+        .collect()
+}
+
+/// The role of a train stop, as named at the end of its station name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StationRole {
+    /// A station that trains are loaded at.
+    Pickup,
+    /// A station that trains are unloaded at.
+    Drop,
+}
+
+impl StationRole {
+    /// The name of the role, as used in station names (e.g. "Drop").
+    pub fn name(&self) -> &'static str {
+        match self {
+            StationRole::Pickup => "Pickup",
+            StationRole::Drop => "Drop",
+        }
+    }
+}
+
+/// The suggested name of a train stop, with a rich-text icon of each ingredient ordered by its share of the train's slots, followed by its role and train length (e.g. "[item=iron-plate][item=steel-plate] Drop 4-car").
+///
+/// # Parameters
+/// * `item_ratios` - The ingredients the station handles.
+/// * `cars` - The number of cargo wagons on the station's trains.
+/// * `role` - Whether trains are loaded or unloaded at the station.
+/// * `factorio_2` - Whether to name items as in Factorio 2.0.
+pub fn station_name(
+    item_ratios: &[IngredientRatio],
+    cars: u32,
+    role: StationRole,
+    factorio_2: bool,
+) -> String {
+    let mut items: Vec<&IngredientRatio> = item_ratios.iter().collect();
+    // Stable, so that ingredients with equal shares keep the order they were given in.
+    items.sort_by(|a, b| b.stack_ratio.total_cmp(&a.stack_ratio));
+
+    let icons: String = items
+        .iter()
+        .map(|item| format!("[item={}]", item_name(&item.ingredient, factorio_2)))
+        .collect();
+    format!("{} {} {}-car", icons, role.name(), cars)
+}
+
+/// Print the suggested names of the loading and unloading stations of each train length.
+///
+/// # Parameters
+/// * `item_ratios` - The ingredients the stations handle.
+/// * `cars` - The cargo wagon counts of the trains.
+pub fn print_station_names(item_ratios: &[IngredientRatio], cars: &[u32]) {
+    println!("{:=^80}", " STATION NAMES ");
+    for &cars in cars {
+        for (label, role) in [
+            ("LOADING", StationRole::Pickup),
+            ("UNLOADING", StationRole::Drop),
+        ] {
+            println!(
+                "{:>40} ---- {}",
+                format!("{} ({} cars)", label, cars),
+                station_name(item_ratios, cars, role, false)
+            );
+        }
+    }
+}
+
+/// Print a blueprint string under a banner naming it, exiting if it cannot be encoded.
+pub fn print_blueprint(title: &str, blueprint: &Blueprint) {
+    match blueprint.encode() {
+        Ok(string) => {
+            println!("{:=^80}", format!(" {} ", title));
+            println!("{string}");
+        }
+        Err(e) => {
+            println!("{e}");
+            std::process::exit(crate::EXIT_IO_ERROR);
+        }
+    }
+}
+
+/// Print a blueprint string of each planned train, each under a banner naming the train.
+///
+/// # Parameters
+/// * `item_ratios` - The ingredients the filters were planned for.
+/// * `trains` - The planned filters of every train.
+/// * `locomotives` - The number of locomotives at the front of each train.
+/// * `schedule` - How long each train waits at its loading station, if the train is to be given a schedule.
+/// * `interrupts` - Whether to write the schedule as Factorio 2.0 interrupts.
+///
+/// # Returns
+/// [Ok] if every blueprint was printed, or [Err]\(message) if an item has no Factorio 2.0 name to write interrupts with.
+pub fn print_train_blueprints(
+    item_ratios: &[IngredientRatio],
+    trains: &[TrainFilters],
+    locomotives: u32,
+    schedule: Option<LoadWait>,
+    interrupts: bool,
+) -> Result<(), String> {
+    // Interrupts are a Factorio 2.0 feature, so blueprints using them are written for Factorio 2.0, items and all.
+    let factorio_2 = interrupts && schedule.is_some();
+    if factorio_2 {
+        check_factorio_2(item_ratios)?;
+    }
+
+    for train in trains {
+        let mut blueprint = train_blueprint(item_ratios, train, locomotives, factorio_2);
+        if let Some(wait) = schedule {
+            blueprint.schedules.push(schedule::train_schedule(
+                item_ratios,
+                train,
+                locomotives,
+                wait,
+                interrupts,
+            ));
+            if interrupts {
+                blueprint.version = FACTORIO_2_VERSION;
+            }
+        }
+
+        print_blueprint(
+            format!("BLUEPRINT ({})", train.title()).as_str(),
+            &blueprint,
+        );
+    }
+
+    Ok(())
+}
+
+/// Print a blueprint string of an unloading station for each planned train, each under a banner naming the train.
+///
+/// # Parameters
+/// * `item_ratios` - The ingredients to be unloaded.
+/// * `trains` - The planned filters of every train.
+/// * `locomotives` - The number of locomotives at the front of each train.
+/// * `chest` - The type of chest to unload into.
+/// * `sides` - The number of sides of the rails to unload from.
+///
+/// # Returns
+/// [Ok] if every blueprint was printed, or [Err]\(message) if a train's wagons cannot all be unloaded.
+pub fn print_station_blueprints(
+    item_ratios: &[IngredientRatio],
+    trains: &[TrainFilters],
+    locomotives: u32,
+    chest: Chest,
+    sides: u32,
+) -> Result<(), String> {
+    for train in trains {
+        print_blueprint(
+            format!("STATION BLUEPRINT ({})", train.title()).as_str(),
+            &station_blueprint(item_ratios, train, locomotives, chest, sides)?,
+        );
+    }
+
+    Ok(())
+}
+
+/// Print a blueprint string of constant combinators for each planned train, each under a banner naming the train.
+///
+/// # Parameters
+/// * `item_ratios` - The ingredients the filters were planned for.
+/// * `trains` - The planned filters of every train.
+/// * `locomotives` - The number of locomotives at the front of each train.
+pub fn print_combinator_blueprints(
+    item_ratios: &[IngredientRatio],
+    trains: &[TrainFilters],
+    locomotives: u32,
+) {
+    for train in trains {
+        print_blueprint(
+            format!("COMBINATOR BLUEPRINT ({})", train.title()).as_str(),
+            &combinator_blueprint(item_ratios, train, locomotives),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        allocation::Allocation, calculate_ratios, rational::Rational, report::Selection, wagon,
+    };
+
+    /// Encode any JSON value as a blueprint string, the way the game does.
+    fn encode_json(value: &serde_json::Value) -> String {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(value.to_string().as_bytes()).unwrap();
+        format!(
+            "{}{}",
+            BLUEPRINT_STRING_VERSION,
+            base64::engine::general_purpose::STANDARD.encode(encoder.finish().unwrap())
+        )
+    }
+
+    /// A blueprint of a single cargo wagon with the given slot filters and a limited chest beside it.
+    fn wagon_blueprint(label: &str, filters: &[(u32, &str)]) -> Blueprint {
+        let mut blueprint = Blueprint::new(label.to_string(), &["cargo-wagon"]);
+        blueprint.push_entity(Entity {
+            orientation: Some(0.0),
+            inventory: Some(Inventory {
+                filters: filters
+                    .iter()
+                    .map(|(index, name)| Filter {
+                        index: *index,
+                        name: name.to_string(),
+                    })
+                    .collect(),
+            }),
+            ..Entity::new("cargo-wagon", Position { x: 1.0, y: 3.0 })
+        });
+        blueprint.push_entity(Entity {
+            bar: Some(12),
+            ..Entity::new("steel-chest", Position { x: 3.5, y: 0.5 })
+        });
+        blueprint
+    }
+
+    #[test]
+    fn encoded_blueprints_decode_to_the_same_blueprint() {
+        let blueprint = wagon_blueprint("Test train", &[(1, "iron-plate"), (2, "copper-plate")]);
+        let string = blueprint.encode().unwrap();
+        assert!(string.starts_with(BLUEPRINT_STRING_VERSION));
+
+        let decoded = decode(string.as_str()).unwrap();
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].label.as_deref(), Some("Test train"));
+
+        let entities = &decoded[0].entities;
+        assert_eq!(entities.len(), 2);
+        assert_eq!(entities[0].name, "cargo-wagon");
+        assert_eq!(entities[0].position.x, 1.0);
+        assert_eq!(entities[0].position.y, 3.0);
+        let filters: Vec<(u32, &str)> = entities[0]
+            .inventory
+            .as_ref()
+            .unwrap()
+            .filters
+            .iter()
+            .map(|filter| (filter.index, filter.name.as_str()))
+            .collect();
+        assert_eq!(filters, vec![(1, "iron-plate"), (2, "copper-plate")]);
+        assert_eq!(entities[1].name, "steel-chest");
+        assert_eq!(entities[1].bar, Some(12));
+    }
+
+    #[test]
+    fn decoding_ignores_surrounding_whitespace() {
+        let string = wagon_blueprint("Test", &[]).encode().unwrap();
+        let decoded = decode(format!("  {string}\n").as_str()).unwrap();
+        assert_eq!(decoded.len(), 1);
+    }
+
+    #[test]
+    fn books_decode_to_their_blueprints_in_order() {
+        let blueprint = |label: &str| {
+            serde_json::to_value(BlueprintString {
+                blueprint: &wagon_blueprint(label, &[(1, "coal")]),
+            })
+            .unwrap()
+        };
+        let book = serde_json::json!({
+            "blueprint_book": {
+                "item": "blueprint-book",
+                "blueprints": [
+                    blueprint("First"),
+                    {
+                        "blueprint_book": {
+                            "item": "blueprint-book",
+                            "blueprints": [blueprint("Second"), blueprint("Third")],
+                        },
+                    },
+                    { "upgrade_planner": { "item": "upgrade-planner" } },
+                    blueprint("Fourth"),
+                ],
+            },
+        });
+
+        let decoded = decode(encode_json(&book).as_str()).unwrap();
+        let labels: Vec<&str> = decoded
+            .iter()
+            .map(|blueprint| blueprint.label.as_deref().unwrap())
+            .collect();
+        assert_eq!(labels, vec!["First", "Second", "Third", "Fourth"]);
+        assert!(decoded
+            .iter()
+            .all(|blueprint| blueprint.entities[0].name == "cargo-wagon"));
+    }
+
+    #[test]
+    fn empty_books_decode_to_no_blueprints() {
+        let book = serde_json::json!({ "blueprint_book": { "item": "blueprint-book" } });
+        assert_eq!(decode(encode_json(&book).as_str()).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn rejects_invalid_blueprint_strings() {
+        let valid = wagon_blueprint("Test", &[]).encode().unwrap();
+        let unknown_version = format!("1{}", &valid[1..]);
+        let not_json = format!(
+            "{}{}",
+            BLUEPRINT_STRING_VERSION,
+            base64::engine::general_purpose::STANDARD.encode(b"not zlib")
+        );
+        for string in ["", "0", "0!!!", unknown_version.as_str(), not_json.as_str()] {
+            assert!(decode(string).is_err(), "{string:?}");
+        }
+    }
+
+    #[test]
+    fn train_blueprints_decode_to_their_planned_filters() {
+        let ingredients = vec![
+            Ingredient::new("iron plate", Rational::from_integer(300), 100).unwrap(),
+            Ingredient::new("steel", Rational::from_integer(100), 100).unwrap(),
+        ];
+        let item_ratios = calculate_ratios(&ingredients, false).unwrap();
+        let selection = Selection {
+            cars: vec![2],
+            same_car: true,
+            different_car: false,
+            extra: false,
+            chest: Chest::Steel,
+        };
+        let train = &wagon::plan_trains(&item_ratios, &selection, false)[0];
+
+        let string = train_blueprint(&item_ratios, train, 1, false)
+            .encode()
+            .unwrap();
+        let decoded = decode(string.as_str()).unwrap();
+        let wagons: Vec<&ImportedEntity> = decoded[0]
+            .entities
+            .iter()
+            .filter(|entity| entity.name == "cargo-wagon")
+            .collect();
+        assert_eq!(wagons.len(), 2);
+        for wagon in wagons {
+            let filters = &wagon.inventory.as_ref().unwrap().filters;
+            let count = |name: &str| filters.iter().filter(|filter| filter.name == name).count();
+            assert_eq!(count("iron-plate"), 30);
+            assert_eq!(count("steel-plate"), 10);
+        }
+    }
+
+    #[test]
+    fn station_blueprints_match_the_reported_chests_and_inserters() {
+        let ingredients = vec![
+            Ingredient::new("iron plate", Rational::from_integer(300), 100).unwrap(),
+            Ingredient::new("steel", Rational::from_integer(100), 100).unwrap(),
+            Ingredient::new("coal", Rational::from_integer(50), 50).unwrap(),
+        ];
+        let item_ratios = calculate_ratios(&ingredients, false).unwrap();
+        let selection = Selection {
+            cars: vec![4],
+            same_car: false,
+            different_car: true,
+            extra: false,
+            chest: Chest::Steel,
+        };
+        let train = &wagon::plan_trains(&item_ratios, &selection, false)[0];
+
+        let string = station_blueprint(&item_ratios, train, 1, Chest::Steel, 1)
+            .unwrap()
+            .encode()
+            .unwrap();
+        let decoded = decode(string.as_str()).unwrap();
+        let entities = &decoded[0].entities;
+        let chest_count = entities
+            .iter()
+            .filter(|entity| entity.name == Chest::Steel.entity_name())
+            .count();
+        assert_eq!(chest_count as u32, chests(train.cars));
+
+        let allocation = Allocation::unloading(&item_ratios, train);
+        assert_eq!(allocation.slots, chest_count as u32);
+        for (item, count) in item_ratios.iter().zip(&allocation.counts) {
+            let name = item_name(&item.ingredient, false);
+            let inserters = entities
+                .iter()
+                .filter(|entity| entity.filters.iter().any(|filter| filter.name == name))
+                .count();
+            assert_eq!(inserters as u32, *count, "{name}");
+        }
+    }
+
+    #[test]
+    fn train_blueprints_put_their_locomotives_first() {
+        let ingredients = vec![
+            Ingredient::new("empty barrel", Rational::from_integer(100), 10).unwrap(),
+            Ingredient::new("widget frame", Rational::from_integer(100), 10).unwrap(),
+        ];
+        let item_ratios = calculate_ratios(&ingredients, false).unwrap();
+        let selection = Selection {
+            cars: vec![3],
+            same_car: true,
+            different_car: false,
+            extra: false,
+            chest: Chest::Steel,
+        };
+        let train = &wagon::plan_trains(&item_ratios, &selection, false)[0];
+
+        let blueprint = train_blueprint(&item_ratios, train, 2, false);
+        assert_eq!(blueprint.label, "2-3 train (SC 3 cars)");
+        let names: Vec<&str> = blueprint
+            .entities
+            .iter()
+            .map(|entity| entity.name)
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "locomotive",
+                "locomotive",
+                "cargo-wagon",
+                "cargo-wagon",
+                "cargo-wagon"
+            ]
+        );
+        for (index, entity) in blueprint.entities.iter().enumerate() {
+            assert_eq!(entity.entity_number, index as u32 + 1);
+            assert_eq!(entity.position.y, index as f64 * 7.0 + 3.0);
+            assert_eq!(entity.orientation, Some(0.0));
+        }
+
+        let filters = |blueprint: &Blueprint| -> Vec<String> {
+            let inventory = blueprint.entities[2].inventory.as_ref().unwrap();
+            assert_eq!(inventory.filters.len(), 40);
+            let mut names: Vec<String> = inventory
+                .filters
+                .iter()
+                .map(|filter| filter.name.clone())
+                .collect();
+            names.dedup();
+            names
+        };
+        assert_eq!(filters(&blueprint), vec!["empty-barrel", "widget-frame"]);
+        let blueprint = train_blueprint(&item_ratios, train, 1, true);
+        assert_eq!(filters(&blueprint), vec!["barrel", "widget-frame"]);
+    }
+
+    #[test]
+    fn combinators_hold_each_wagons_item_counts() {
+        let ingredients = vec![
+            Ingredient::new("iron plate", Rational::from_integer(300), 100).unwrap(),
+            Ingredient::new("steel", Rational::from_integer(100), 100).unwrap(),
+        ];
+        let item_ratios = calculate_ratios(&ingredients, false).unwrap();
+        let selection = Selection {
+            cars: vec![2],
+            same_car: false,
+            different_car: true,
+            extra: false,
+            chest: Chest::Steel,
+        };
+        let train = &wagon::plan_trains(&item_ratios, &selection, false)[0];
+
+        let blueprint = combinator_blueprint(&item_ratios, train, 1);
+        assert_eq!(blueprint.entities.len(), 2);
+        for (combinator, (index, wagon)) in blueprint.entities.iter().zip((1..).zip(&train.wagons))
+        {
+            assert_eq!(combinator.name, "constant-combinator");
+            assert_eq!(combinator.position.y, index as f64 * 7.0 + 3.0);
+            let signals: Vec<(String, i32)> = combinator
+                .control_behavior
+                .as_ref()
+                .unwrap()
+                .filters
+                .iter()
+                .map(|filter| (filter.signal.name.clone(), filter.count))
+                .collect();
+            let expected: Vec<(String, i32)> = [("iron-plate", 0), ("steel-plate", 1)]
+                .into_iter()
+                .filter(|(_, item)| wagon.count(*item) > 0)
+                .map(|(name, item)| (name.to_string(), wagon.count(item) as i32 * 100))
+                .collect();
+            assert_eq!(signals, expected);
+        }
+    }
+
+    #[test]
+    fn combinators_overflow_into_the_next_combinator() {
+        let signals: Vec<(Signal, i32)> = (0..25)
+            .map(|count| {
+                (
+                    Signal {
+                        kind: "item",
+                        name: format!("item-{count}"),
+                    },
+                    count,
+                )
+            })
+            .collect();
+        let mut blueprint = Blueprint::new("Test".into(), &["constant-combinator"]);
+        blueprint.push_combinators(Position { x: 0.5, y: 0.5 }, &signals);
+
+        assert_eq!(blueprint.entities.len(), 2);
+        let filters = |entity: &Entity| entity.control_behavior.as_ref().unwrap().filters.len();
+        assert_eq!(filters(&blueprint.entities[0]), 20);
+        assert_eq!(filters(&blueprint.entities[1]), 5);
+        assert_eq!(blueprint.entities[1].position.x, 1.5);
+        let second = &blueprint.entities[1]
+            .control_behavior
+            .as_ref()
+            .unwrap()
+            .filters[0];
+        assert_eq!((second.index, second.count), (1, 20));
+    }
+
+    #[test]
+    fn station_names_order_icons_by_share() {
+        let ingredients = vec![
+            Ingredient::new("steel", Rational::from_integer(100), 100).unwrap(),
+            Ingredient::new("iron plate", Rational::from_integer(300), 100).unwrap(),
+            Ingredient::new("empty barrel", Rational::from_integer(10), 10).unwrap(),
+        ];
+        let item_ratios = calculate_ratios(&ingredients, false).unwrap();
+
+        assert_eq!(
+            station_name(&item_ratios, 4, StationRole::Drop, false),
+            "[item=iron-plate][item=steel-plate][item=empty-barrel] Drop 4-car"
+        );
+        assert_eq!(
+            station_name(&item_ratios, 2, StationRole::Pickup, true),
+            "[item=iron-plate][item=steel-plate][item=barrel] Pickup 2-car"
+        );
+    }
+
+    #[test]
+    fn station_names_keep_equal_shares_in_order() {
+        let ingredients = vec![
+            Ingredient::new("copper plate", Rational::from_integer(100), 100).unwrap(),
+            Ingredient::new("iron plate", Rational::from_integer(100), 100).unwrap(),
+        ];
+        let item_ratios = calculate_ratios(&ingredients, false).unwrap();
+        assert_eq!(
+            station_name(&item_ratios, 1, StationRole::Drop, false),
+            "[item=copper-plate][item=iron-plate] Drop 1-car"
+        );
+
+        let selection = Selection {
+            cars: vec![1],
+            same_car: true,
+            different_car: false,
+            extra: false,
+            chest: Chest::Steel,
+        };
+        let train = &wagon::plan_trains(&item_ratios, &selection, false)[0];
+        let station = station_blueprint(&item_ratios, train, 1, Chest::Steel, 1).unwrap();
+        let stop = station
+            .entities
+            .iter()
+            .find(|entity| entity.name == "train-stop")
+            .unwrap();
+        assert_eq!(
+            stop.station.as_deref(),
+            Some("[item=copper-plate][item=iron-plate] Drop 1-car")
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use std::ops::RangeInclusive;
+
+use crate::{
+    allocation::Allocation,
+    wagon::{self, train_slots, WAGON_SLOTS},
+    IngredientRatio,
+};
+
+/// The width of the label column of the comparison table.
+const LABEL_WIDTH: usize = 24;
+
+/// The width of each train length column of the comparison table.
+const COLUMN_WIDTH: usize = 7;
+
+/// The slot allocations of a range of train lengths.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    /// Whether each wagon follows the same filter ("SC" filtering).
+    pub same_car: bool,
+    /// The largest acceptable relative ratio error (e.g. 0.05 for 5%).
+    pub target_error: f64,
+    /// The cargo wagon count of each train compared, with its slot allocation.
+    pub allocations: Vec<(u32, Allocation)>,
+    /// The cargo wagon count of the smallest train within the target ratio error, if any.
+    pub best: Option<u32>,
+}
+
+impl Comparison {
+    /// Allocate slots for each train length in a range, and find the smallest train within the target ratio error.
+    ///
+    /// # Parameters
+    /// * `item_ratios` - The ingredients to allocate slots to.
+    /// * `cars` - The range of cargo wagon counts to compare.
+    /// * `same_car` - Whether each wagon must follow the same filter ("SC" filtering).
+    /// * `target_error` - The largest acceptable relative ratio error (e.g. 0.05 for 5%).
+    pub fn new(
+        item_ratios: &[IngredientRatio],
+        cars: RangeInclusive<u32>,
+        same_car: bool,
+        target_error: f64,
+    ) -> Self {
+        let allocations: Vec<(u32, Allocation)> = cars
+            .map(|cars| {
+                let allocation = if same_car {
+                    Allocation::repeated(item_ratios, WAGON_SLOTS, cars)
+                } else {
+                    Allocation::new(item_ratios, train_slots(cars))
+                };
+                (cars, allocation)
+            })
+            .collect();
+
+        // The smallest train that meets the target ratio error.
+        let best = allocations
+            .iter()
+            .find(|(_, allocation)| allocation.max_ratio_error(item_ratios) <= target_error)
+            .map(|(cars, _)| *cars);
+
+        Comparison {
+            same_car,
+            target_error,
+            allocations,
+            best,
+        }
+    }
+}
+
+/// Print the slot allocations of a range of train lengths side by side, along with the ratio error, wasted slots and limiting ingredient of each. The smallest train within the target ratio error is marked.
+///
+/// # Parameters
+/// * `item_ratios` - The ingredients the comparison was calculated over.
+/// * `comparison` - The comparison to print.
+pub fn print_comparison(item_ratios: &[IngredientRatio], comparison: &Comparison) {
+    let Comparison {
+        same_car,
+        target_error,
+        allocations,
+        best,
+    } = comparison;
+    let (same_car, target_error, best) = (*same_car, *target_error, *best);
+
+    let mode = if same_car { "SC" } else { "DC" };
+    println!("{:=^80}", format!(" COMPARE ({}) ", mode));
+
+    print_row(
+        "",
+        allocations.iter().map(|(cars, _)| {
+            let marker = if Some(*cars) == best { "*" } else { "" };
+            format!("{}{}-car", marker, cars)
+        }),
+    );
+
+    for (index, item) in item_ratios.iter().enumerate() {
+        print_row(
+            format!("{} {}", wagon::slot_symbol(index), item.ingredient.name).as_str(),
+            allocations
+                .iter()
+                .map(|(_, allocation)| allocation.counts[index].to_string()),
+        );
+    }
+
+    print_row(
+        "RATIO ERROR",
+        allocations.iter().map(|(_, allocation)| {
+            format!("{:.1}%", allocation.max_ratio_error(item_ratios) * 100.0)
+        }),
+    );
+
+    print_row(
+        "WASTED SLOTS",
+        allocations
+            .iter()
+            .map(|(_, allocation)| format!("{:.1}", allocation.wasted_slots(item_ratios))),
+    );
+
+    print_row(
+        "LIMITING",
+        allocations.iter().map(|(_, allocation)| {
+            match allocation.limiting_ingredient(item_ratios) {
+                Some(index) => wagon::slot_symbol(index).to_string(),
+                None => "-".into(),
+            }
+        }),
+    );
+
+    match best {
+        Some(cars) => println!(
+            "* {}-car is the smallest train within {:.1}% ratio error.",
+            cars,
+            target_error * 100.0
+        ),
+        None => println!(
+            "No train in this range is within {:.1}% ratio error.",
+            target_error * 100.0
+        ),
+    }
+}
+
+/// Print a row of the comparison table, truncating the label to fit its column.
+fn print_row(label: &str, cells: impl Iterator<Item = String>) {
+    let label: String = label.chars().take(LABEL_WIDTH - 1).collect();
+    let cells: String = cells
+        .map(|cell| format!("{:>width$}", cell, width = COLUMN_WIDTH))
+        .collect();
+    println!("{:<width$}{}", label, cells, width = LABEL_WIDTH);
+}
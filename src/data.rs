@@ -1,125 +1,307 @@
+/// Hand-written bindings of the names of items in the Factorio vanilla game to their stack sizes. Names are the in-game American English localized names, followed by common colloquialisms.
+pub const STACK_SIZES: &[(&str, u32)] = &[
+    // Logistics
+    ("stone brick", 100),
+    ("concrete", 100),
+    ("hazard concrete", 100),
+    ("refined concrete", 100),
+    ("refined hazard concrete", 100),
+    ("landfill", 100),
+    // Production
+    ("repair pack", 100),
+    ("speed module", 50),
+    ("speed module 2", 50),
+    ("speed module 3", 50),
+    ("efficiency module", 50),
+    ("efficiency module 2", 50),
+    ("efficiency module 3", 50),
+    ("productivity module", 50),
+    ("productivity module 2", 50),
+    ("productivity module 3", 50),
+    ("satellite", 1),
+    // Intermediate products
+    ("wood", 100),
+    ("coal", 50),
+    ("stone", 50),
+    ("iron ore", 50),
+    ("copper ore", 50),
+    ("uranium ore", 50),
+    ("raw fish", 100),
+    ("iron plate", 100),
+    ("copper plate", 100),
+    ("solid fuel", 50),
+    ("steel plate", 100),
+    ("plastic bar", 100),
+    ("sulfur", 50),
+    ("battery", 200),
+    ("explosives", 50),
+    ("crude oil barrel", 10),
+    ("heavy oil barrel", 10),
+    ("light oil barrel", 10),
+    ("lubricant barrel", 10),
+    ("petroleum gas barrel", 10),
+    ("sulfuric acid barrel", 10),
+    ("water barrel", 10),
+    ("copper cable", 200),
+    ("iron stick", 100),
+    ("iron gear wheel", 100),
+    ("empty barrel", 10),
+    ("electronic circuit", 200),
+    ("advanced circuit", 200),
+    ("processing unit", 100),
+    ("engine unit", 50),
+    ("electric engine unit", 50),
+    ("flying robot frame", 50),
+    ("rocket control unit", 10),
+    ("low density structure", 10),
+    ("rocket fuel", 10),
+    ("nuclear fuel", 10),
+    ("uranium-235", 100),
+    ("uranium-238", 100),
+    ("uranium fuel cell", 50),
+    ("used-up uranium fuel cell", 50),
+    ("automation science pack", 200),
+    ("logistic science pack", 200),
+    ("military science pack", 200),
+    ("chemical science pack", 200),
+    ("production science pack", 200),
+    ("utility science pack", 200),
+    ("space science pack", 2000),
+    // Combat
+    ("firearm magazine", 200),
+    ("piercing rounds magazine", 200),
+    ("uranium rounds magazine", 200),
+    ("shotgun shells", 200),
+    ("piercing shotgun shells", 200),
+    ("cannon shell", 200),
+    ("explosive cannon shell", 200),
+    ("uranium cannon shell", 200),
+    ("explosive uranium cannon shell", 200),
+    ("artillery shell", 1),
+    ("rocket", 200),
+    ("explosive rocket", 200),
+    ("atomic bomb", 10),
+    ("flamethrower ammo", 100),
+    // Colloquialisms
+    ("speed module 1", 50),
+    ("efficiency module 1", 50),
+    ("productivity module 1", 50),
+    ("fish", 100),
+    ("steel", 100),
+    ("plastic", 100),
+    ("gear", 100),
+    ("gear wheel", 100),
+    ("green circuit", 200),
+    ("red circuit", 200),
+    ("blue circuit", 100),
+    ("engine", 50),
+    ("electric engine", 50),
+    ("robot frame", 50),
+    ("frf", 50),
+    ("rcu", 10),
+    ("lds", 10),
+    ("red science", 200),
+    ("automation science", 200),
+    ("green science", 200),
+    ("logistic science", 200),
+    ("black science", 200),
+    ("military science", 200),
+    ("blue science", 200),
+    ("chemical science", 200),
+    ("purple science", 200),
+    ("production science", 200),
+    ("yellow science", 200),
+    ("utility science", 200),
+    ("white science", 2000),
+    ("space science", 2000),
+    ("piercing rounds", 200),
+    ("uranium rounds", 200),
+];
+
 /// Fetch from hand-written bindings the stack size of an item in the Factorio vanilla game from its in-game American English localized name. These bindings also include common colloquialisms.
 ///
 /// # Returns
 /// If it is successful, it will return the stack size of the item as [Some]\(u32), if the stack size is not defined in this function, it will instead return [None].
 pub fn fetch_item_stack_size(item_name: &str) -> Option<u32> {
+    STACK_SIZES
+        .iter()
+        .find(|(name, _)| *name == item_name)
+        .map(|(_, stack_size)| *stack_size)
+}
+
+/// The canonical name of an item as typed, so that the same item is always known by the same name: trimmed, in lowercase with single spaces, and the in-game localized name of known items typed by a colloquialism or their prototype name (e.g. " Iron-Plate " becomes "iron plate", and "steel" becomes "steel plate").
+pub fn canonical_item_name(item_name: &str) -> String {
+    let name = item_name
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase();
+
+    // Every name of a known item leads to its prototype name, and from there back to its localized name.
+    let prototype = match fetch_item_prototype_name(name.as_str()) {
+        Some(prototype) => prototype.to_string(),
+        None => name.clone(),
+    };
+    let localized = fetch_item_localized_name(prototype.as_str());
+    match fetch_item_stack_size(localized.as_str()) {
+        Some(_) => localized,
+        None => name,
+    }
+}
+
+/// Fetch the internal prototype name (e.g. "iron-plate") of an item in the Factorio vanilla game from its in-game American English localized name, as used in blueprints and circuit signals. These bindings include the same colloquialisms as [fetch_item_stack_size].
+///
+/// # Returns
+/// If it is successful, it will return the prototype name of the item as [Some]\(&str), if the item is not defined in this function, it will instead return [None].
+pub fn fetch_item_prototype_name(item_name: &str) -> Option<&'static str> {
     match item_name {
         // Logistics
-        "stone brick" => Some(100),
-        "concrete" => Some(100),
-        "hazard concrete" => Some(100),
-        "refined concrete" => Some(100),
-        "refined hazard concrete" => Some(100),
-        "landfill" => Some(100),
+        "stone brick" => Some("stone-brick"),
+        "concrete" => Some("concrete"),
+        "hazard concrete" => Some("hazard-concrete"),
+        "refined concrete" => Some("refined-concrete"),
+        "refined hazard concrete" => Some("refined-hazard-concrete"),
+        "landfill" => Some("landfill"),
         // Production
-        "repair pack" => Some(100),
-        "speed module" => Some(50),
-        "speed module 2" => Some(50),
-        "speed module 3" => Some(50),
-        "efficiency module" => Some(50),
-        "efficiency module 2" => Some(50),
-        "efficiency module 3" => Some(50),
-        "productivity module" => Some(50),
-        "productivity module 2" => Some(50),
-        "productivity module 3" => Some(50),
-        "satellite" => Some(1),
+        "repair pack" => Some("repair-pack"),
+        "speed module" => Some("speed-module"),
+        "speed module 2" => Some("speed-module-2"),
+        "speed module 3" => Some("speed-module-3"),
+        "efficiency module" => Some("effectivity-module"),
+        "efficiency module 2" => Some("effectivity-module-2"),
+        "efficiency module 3" => Some("effectivity-module-3"),
+        "productivity module" => Some("productivity-module"),
+        "productivity module 2" => Some("productivity-module-2"),
+        "productivity module 3" => Some("productivity-module-3"),
+        "satellite" => Some("satellite"),
         // Intermediate products
-        "wood" => Some(100),
-        "coal" => Some(50),
-        "stone" => Some(50),
-        "iron ore" => Some(50),
-        "copper ore" => Some(50),
-        "uranium ore" => Some(50),
-        "raw fish" => Some(100),
-        "iron plate" => Some(100),
-        "copper plate" => Some(100),
-        "solid fuel" => Some(50),
-        "steel plate" => Some(100),
-        "plastic bar" => Some(100),
-        "sulfur" => Some(50),
-        "battery" => Some(200),
-        "explosives" => Some(50),
-        "crude oil barrel" => Some(10),
-        "heavy oil barrel" => Some(10),
-        "light oil barrel" => Some(10),
-        "lubricant barrel" => Some(10),
-        "petroleum gas barrel" => Some(10),
-        "sulfuric acid barrel" => Some(10),
-        "water barrel" => Some(10),
-        "copper cable" => Some(200),
-        "iron stick" => Some(100),
-        "iron gear wheel" => Some(100),
-        "empty barrel" => Some(10),
-        "electronic circuit" => Some(200),
-        "advanced circuit" => Some(200),
-        "processing unit" => Some(100),
-        "engine unit" => Some(50),
-        "electric engine unit" => Some(50),
-        "flying robot frame" => Some(50),
-        "rocket control unit" => Some(10),
-        "low density structure" => Some(10),
-        "rocket fuel" => Some(10),
-        "nuclear fuel" => Some(10),
-        "uranium-235" => Some(100),
-        "uranium-238" => Some(100),
-        "uranium fuel cell" => Some(50),
-        "used-up uranium fuel cell" => Some(50),
-        "automation science pack" => Some(200),
-        "logistic science pack" => Some(200),
-        "military science pack" => Some(200),
-        "chemical science pack" => Some(200),
-        "production science pack" => Some(200),
-        "utility science pack" => Some(200),
-        "space science pack" => Some(2000),
+        "wood" => Some("wood"),
+        "coal" => Some("coal"),
+        "stone" => Some("stone"),
+        "iron ore" => Some("iron-ore"),
+        "copper ore" => Some("copper-ore"),
+        "uranium ore" => Some("uranium-ore"),
+        "raw fish" => Some("raw-fish"),
+        "iron plate" => Some("iron-plate"),
+        "copper plate" => Some("copper-plate"),
+        "solid fuel" => Some("solid-fuel"),
+        "steel plate" => Some("steel-plate"),
+        "plastic bar" => Some("plastic-bar"),
+        "sulfur" => Some("sulfur"),
+        "battery" => Some("battery"),
+        "explosives" => Some("explosives"),
+        "crude oil barrel" => Some("crude-oil-barrel"),
+        "heavy oil barrel" => Some("heavy-oil-barrel"),
+        "light oil barrel" => Some("light-oil-barrel"),
+        "lubricant barrel" => Some("lubricant-barrel"),
+        "petroleum gas barrel" => Some("petroleum-gas-barrel"),
+        "sulfuric acid barrel" => Some("sulfuric-acid-barrel"),
+        "water barrel" => Some("water-barrel"),
+        "copper cable" => Some("copper-cable"),
+        "iron stick" => Some("iron-stick"),
+        "iron gear wheel" => Some("iron-gear-wheel"),
+        "empty barrel" => Some("empty-barrel"),
+        "electronic circuit" => Some("electronic-circuit"),
+        "advanced circuit" => Some("advanced-circuit"),
+        "processing unit" => Some("processing-unit"),
+        "engine unit" => Some("engine-unit"),
+        "electric engine unit" => Some("electric-engine-unit"),
+        "flying robot frame" => Some("flying-robot-frame"),
+        "rocket control unit" => Some("rocket-control-unit"),
+        "low density structure" => Some("low-density-structure"),
+        "rocket fuel" => Some("rocket-fuel"),
+        "nuclear fuel" => Some("nuclear-fuel"),
+        "uranium-235" => Some("uranium-235"),
+        "uranium-238" => Some("uranium-238"),
+        "uranium fuel cell" => Some("uranium-fuel-cell"),
+        "used-up uranium fuel cell" => Some("used-up-uranium-fuel-cell"),
+        "automation science pack" => Some("automation-science-pack"),
+        "logistic science pack" => Some("logistic-science-pack"),
+        "military science pack" => Some("military-science-pack"),
+        "chemical science pack" => Some("chemical-science-pack"),
+        "production science pack" => Some("production-science-pack"),
+        "utility science pack" => Some("utility-science-pack"),
+        "space science pack" => Some("space-science-pack"),
         // Combat
-        "firearm magazine" => Some(200),
-        "piercing rounds magazine" => Some(200),
-        "uranium rounds magazine" => Some(200),
-        "shotgun shells" => Some(200),
-        "piercing shotgun shells" => Some(200),
-        "cannon shell" => Some(200),
-        "explosive cannon shell" => Some(200),
-        "uranium cannon shell" => Some(200),
-        "explosive uranium cannon shell" => Some(200),
-        "artillery shell" => Some(1),
-        "rocket" => Some(200),
-        "explosive rocket" => Some(200),
-        "atomic bomb" => Some(10),
-        "flamethrower ammo" => Some(100),
+        "firearm magazine" => Some("firearm-magazine"),
+        "piercing rounds magazine" => Some("piercing-rounds-magazine"),
+        "uranium rounds magazine" => Some("uranium-rounds-magazine"),
+        "shotgun shells" => Some("shotgun-shell"),
+        "piercing shotgun shells" => Some("piercing-shotgun-shell"),
+        "cannon shell" => Some("cannon-shell"),
+        "explosive cannon shell" => Some("explosive-cannon-shell"),
+        "uranium cannon shell" => Some("uranium-cannon-shell"),
+        "explosive uranium cannon shell" => Some("explosive-uranium-cannon-shell"),
+        "artillery shell" => Some("artillery-shell"),
+        "rocket" => Some("rocket"),
+        "explosive rocket" => Some("explosive-rocket"),
+        "atomic bomb" => Some("atomic-bomb"),
+        "flamethrower ammo" => Some("flamethrower-ammo"),
         // Colloquialisms
-        "speed module 1" => Some(50),
-        "efficiency module 1" => Some(50),
-        "productivity module 1" => Some(50),
-        "fish" => Some(100),
-        "steel" => Some(100),
-        "plastic" => Some(100),
-        "gear" => Some(100),
-        "gear wheel" => Some(100),
-        "green circuit" => Some(200),
-        "red circuit" => Some(200),
-        "blue circuit" => Some(100),
-        "engine" => Some(50),
-        "electric engine" => Some(50),
-        "robot frame" => Some(50),
-        "frf" => Some(50),
-        "rcu" => Some(10),
-        "lds" => Some(10),
-        "red science" => Some(200),
-        "automation science" => Some(200),
-        "green science" => Some(200),
-        "logistic science" => Some(200),
-        "black science" => Some(200),
-        "military science" => Some(200),
-        "blue science" => Some(200),
-        "chemical science" => Some(200),
-        "purple science" => Some(200),
-        "production science" => Some(200),
-        "yellow science" => Some(200),
-        "utility science" => Some(200),
-        "white science" => Some(2000),
-        "space science" => Some(2000),
-        "piercing rounds" => Some(200),
-        "uranium rounds" => Some(200),
+        "speed module 1" => Some("speed-module"),
+        "efficiency module 1" => Some("effectivity-module"),
+        "productivity module 1" => Some("productivity-module"),
+        "fish" => Some("raw-fish"),
+        "steel" => Some("steel-plate"),
+        "plastic" => Some("plastic-bar"),
+        "gear" => Some("iron-gear-wheel"),
+        "gear wheel" => Some("iron-gear-wheel"),
+        "green circuit" => Some("electronic-circuit"),
+        "red circuit" => Some("advanced-circuit"),
+        "blue circuit" => Some("processing-unit"),
+        "engine" => Some("engine-unit"),
+        "electric engine" => Some("electric-engine-unit"),
+        "robot frame" => Some("flying-robot-frame"),
+        "frf" => Some("flying-robot-frame"),
+        "rcu" => Some("rocket-control-unit"),
+        "lds" => Some("low-density-structure"),
+        "red science" => Some("automation-science-pack"),
+        "automation science" => Some("automation-science-pack"),
+        "green science" => Some("logistic-science-pack"),
+        "logistic science" => Some("logistic-science-pack"),
+        "black science" => Some("military-science-pack"),
+        "military science" => Some("military-science-pack"),
+        "blue science" => Some("chemical-science-pack"),
+        "chemical science" => Some("chemical-science-pack"),
+        "purple science" => Some("production-science-pack"),
+        "production science" => Some("production-science-pack"),
+        "yellow science" => Some("utility-science-pack"),
+        "utility science" => Some("utility-science-pack"),
+        "white science" => Some("space-science-pack"),
+        "space science" => Some("space-science-pack"),
+        "piercing rounds" => Some("piercing-rounds-magazine"),
+        "uranium rounds" => Some("uranium-rounds-magazine"),
         _ => None,
     }
 }
+
+/// Fetch the internal prototype name of an item in Factorio 2.0 from its prototype name in Factorio 1.1, as returned by [fetch_item_prototype_name]. Items not renamed in 2.0 keep their name (e.g. "iron-plate"), and renamed items are given their new name (e.g. "empty-barrel" becomes "barrel").
+///
+/// # Returns
+/// The prototype name of the item in Factorio 2.0 as [Some]\(&str), or [None] if the item was removed in Factorio 2.0.
+pub fn fetch_item_prototype_name_2(prototype_name: &str) -> Option<&str> {
+    match prototype_name {
+        "effectivity-module" => Some("efficiency-module"),
+        "effectivity-module-2" => Some("efficiency-module-2"),
+        "effectivity-module-3" => Some("efficiency-module-3"),
+        "empty-barrel" => Some("barrel"),
+        "used-up-uranium-fuel-cell" => Some("depleted-uranium-fuel-cell"),
+        "rocket-control-unit" => None,
+        _ => Some(prototype_name),
+    }
+}
+
+/// Fetch the in-game American English localized name of an item in the Factorio vanilla game from its internal prototype name, reversing [fetch_item_prototype_name]. Items not defined in this function are assumed to be named after their prototype name with spaces for hyphens (e.g. "iron-plate" becomes "iron plate").
+pub fn fetch_item_localized_name(prototype_name: &str) -> String {
+    match prototype_name {
+        "effectivity-module" => "efficiency module".into(),
+        "effectivity-module-2" => "efficiency module 2".into(),
+        "effectivity-module-3" => "efficiency module 3".into(),
+        "uranium-235" => "uranium-235".into(),
+        "uranium-238" => "uranium-238".into(),
+        "used-up-uranium-fuel-cell" => "used-up uranium fuel cell".into(),
+        "shotgun-shell" => "shotgun shells".into(),
+        "piercing-shotgun-shell" => "piercing shotgun shells".into(),
+        _ => prototype_name.replace('-', " "),
+    }
+}
@@ -0,0 +1,293 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    blueprint::{self, Blueprint, Position, Signal},
+    wagon::TrainFilters,
+    IngredientRatio,
+};
+
+/// The most trains a station can allow at once, as dispatchers read it from a 32-bit signal.
+pub const MAX_TRAIN_LIMIT: u32 = i32::MAX as u32;
+
+/// A train dispatcher mod, which sends trains between stations from the signals set at each station.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Dispatcher {
+    /// LTN (Logistic Train Network).
+    Ltn,
+    /// Cybersyn.
+    Cybersyn,
+}
+
+impl Dispatcher {
+    /// The name of the dispatcher, as printed in banners (e.g. "LTN").
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dispatcher::Ltn => "LTN",
+            Dispatcher::Cybersyn => "CYBERSYN",
+        }
+    }
+}
+
+impl std::str::FromStr for Dispatcher {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ltn" => Ok(Dispatcher::Ltn),
+            "cybersyn" => Ok(Dispatcher::Cybersyn),
+            _ => Err(format!(
+                "unknown dispatcher \"{s}\", expected ltn or cybersyn"
+            )),
+        }
+    }
+}
+
+/// The settings of one type of station for a dispatcher.
+#[derive(Debug, Clone)]
+pub struct StationSettings {
+    /// The type of station, as printed in its banner (e.g. "REQUESTER").
+    pub role: &'static str,
+    /// The signals to output to the station.
+    pub signals: Vec<(Signal, i32)>,
+    /// The number of trains allowed at the station at once.
+    pub train_limit: u32,
+}
+
+/// Derive the requester (unloading) and provider (loading) station settings of a train from the number of each item it carries.
+///
+/// The requester asks for a full train of each item for every train it allows. Both dispatchers take a single threshold per station rather than one per item, so the requester and provider thresholds are the smallest item's count in a full train: no train is sent for less than that, but one can be sent before a full load of the larger items is requested or available. With LTN, trains are also restricted to the train's length.
+///
+/// # Parameters
+/// * `dispatcher` - The dispatcher the signals are for.
+/// * `item_ratios` - The ingredients the filters were planned for.
+/// * `train` - The planned filters of every wagon of the train.
+/// * `locomotives` - The number of locomotives at the front of the train.
+/// * `train_limit` - The number of trains allowed at each station at once.
+pub fn station_settings(
+    dispatcher: Dispatcher,
+    item_ratios: &[IngredientRatio],
+    train: &TrainFilters,
+    locomotives: u32,
+    train_limit: u32,
+) -> Vec<StationSettings> {
+    let items = blueprint::item_signals(item_ratios, false, |index| train.count(index));
+    let threshold = items.iter().map(|(_, count)| *count).min().unwrap_or(0);
+    let length = i32::try_from(locomotives.saturating_add(train.cars)).unwrap_or(i32::MAX);
+    let max_trains = i32::try_from(train_limit).unwrap_or(i32::MAX);
+
+    let virtual_signal = |name: &str, count: i32| {
+        (
+            Signal {
+                kind: "virtual",
+                name: name.to_string(),
+            },
+            count,
+        )
+    };
+
+    let requests = items.iter().map(|(signal, count)| {
+        let count = count.saturating_mul(max_trains);
+        (signal.clone(), -count)
+    });
+
+    match dispatcher {
+        Dispatcher::Ltn => {
+            let limits = [
+                virtual_signal("ltn-min-train-length", length),
+                virtual_signal("ltn-max-train-length", length),
+                virtual_signal("ltn-max-trains", max_trains),
+            ];
+
+            vec![
+                StationSettings {
+                    role: "REQUESTER",
+                    signals: requests
+                        .chain([virtual_signal("ltn-requester-threshold", threshold)])
+                        .chain(limits.clone())
+                        .collect(),
+                    train_limit,
+                },
+                StationSettings {
+                    role: "PROVIDER",
+                    signals: [virtual_signal("ltn-provider-threshold", threshold)]
+                        .into_iter()
+                        .chain(limits)
+                        .collect(),
+                    train_limit,
+                },
+            ]
+        }
+        Dispatcher::Cybersyn => vec![
+            StationSettings {
+                role: "REQUESTER",
+                signals: requests
+                    .chain([virtual_signal("cybersyn-request-threshold", threshold)])
+                    .collect(),
+                train_limit,
+            },
+            StationSettings {
+                role: "PROVIDER",
+                signals: vec![virtual_signal("cybersyn-request-threshold", threshold)],
+                train_limit,
+            },
+        ],
+    }
+}
+
+/// Build a blueprint of constant combinators holding the signals of each type of station, one row of combinators per type.
+///
+/// # Parameters
+/// * `dispatcher` - The dispatcher the signals are for.
+/// * `train` - The train the stations are for.
+/// * `stations` - The settings of each type of station.
+pub fn dispatcher_blueprint(
+    dispatcher: Dispatcher,
+    train: &TrainFilters,
+    stations: &[StationSettings],
+) -> Blueprint {
+    let mut blueprint = Blueprint::new(
+        format!("{} signals ({})", dispatcher.name(), train.title()),
+        &["constant-combinator"],
+    );
+
+    for (row, station) in stations
+        .iter()
+        .filter(|station| !station.signals.is_empty())
+        .enumerate()
+    {
+        blueprint.push_combinators(
+            Position {
+                x: 0.5,
+                y: row as f64 * 2.0 + 0.5,
+            },
+            &station.signals,
+        );
+    }
+
+    blueprint
+}
+
+/// Print the dispatcher settings of each type of station for each planned train, followed by a blueprint string of constant combinators holding their signals.
+///
+/// # Parameters
+/// * `dispatcher` - The dispatcher the settings are for.
+/// * `item_ratios` - The ingredients the filters were planned for.
+/// * `trains` - The planned filters of every train.
+/// * `locomotives` - The number of locomotives at the front of each train.
+/// * `train_limit` - The number of trains allowed at each station at once.
+pub fn print_dispatcher_settings(
+    dispatcher: Dispatcher,
+    item_ratios: &[IngredientRatio],
+    trains: &[TrainFilters],
+    locomotives: u32,
+    train_limit: u32,
+) {
+    for train in trains {
+        let stations = station_settings(dispatcher, item_ratios, train, locomotives, train_limit);
+        println!(
+            "{:=^80}",
+            format!(" {} ({}) ", dispatcher.name(), train.title())
+        );
+
+        for station in &stations {
+            println!("{:-^80}", format!(" {} ", station.role));
+            for (signal, count) in &station.signals {
+                println!("{:>40} ---- {}", signal.name, count);
+            }
+            println!("{:>40} ---- {}", "train limit", station.train_limit);
+        }
+
+        blueprint::print_blueprint(
+            format!("{} BLUEPRINT ({})", dispatcher.name(), train.title()).as_str(),
+            &dispatcher_blueprint(dispatcher, train, &stations),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        calculate_ratios,
+        rational::Rational,
+        report::{Chest, Selection},
+        wagon, Ingredient,
+    };
+
+    /// A 2-car SC train of 30 stacks of iron plate to 10 of steel per wagon.
+    fn train() -> (Vec<IngredientRatio>, TrainFilters) {
+        let ingredients = vec![
+            Ingredient::new("iron plate", Rational::from_integer(300), 100).unwrap(),
+            Ingredient::new("steel", Rational::from_integer(100), 100).unwrap(),
+        ];
+        let item_ratios = calculate_ratios(&ingredients, false).unwrap();
+        let selection = Selection {
+            cars: vec![2],
+            same_car: true,
+            different_car: false,
+            extra: false,
+            chest: Chest::Steel,
+        };
+        let train = wagon::plan_trains(&item_ratios, &selection, false).remove(0);
+        (item_ratios, train)
+    }
+
+    /// The settings of each station of the [train] for a dispatcher.
+    fn settings(dispatcher: Dispatcher, train_limit: u32) -> Vec<StationSettings> {
+        let (item_ratios, train) = train();
+        station_settings(dispatcher, &item_ratios, &train, 1, train_limit)
+    }
+
+    /// The value of the signal of the given name, if it is set.
+    fn signal(station: &StationSettings, name: &str) -> Option<i32> {
+        station
+            .signals
+            .iter()
+            .find(|(signal, _)| signal.name == name)
+            .map(|(_, count)| *count)
+    }
+
+    #[test]
+    fn ltn_requests_full_trains_within_the_train_length() {
+        let stations = settings(Dispatcher::Ltn, 2);
+        let [requester, provider] = &stations[..] else {
+            panic!("expected a requester and a provider");
+        };
+
+        assert_eq!(signal(requester, "iron-plate"), Some(-12000));
+        assert_eq!(signal(requester, "steel-plate"), Some(-4000));
+        assert_eq!(signal(requester, "ltn-requester-threshold"), Some(2000));
+        assert_eq!(signal(provider, "ltn-provider-threshold"), Some(2000));
+        assert_eq!(signal(provider, "iron-plate"), None);
+        for station in [requester, provider] {
+            assert_eq!(signal(station, "ltn-min-train-length"), Some(3));
+            assert_eq!(signal(station, "ltn-max-train-length"), Some(3));
+            assert_eq!(signal(station, "ltn-max-trains"), Some(2));
+            assert_eq!(station.train_limit, 2);
+        }
+    }
+
+    #[test]
+    fn cybersyn_sets_both_stations_thresholds() {
+        let stations = settings(Dispatcher::Cybersyn, 1);
+        let [requester, provider] = &stations[..] else {
+            panic!("expected a requester and a provider");
+        };
+
+        assert_eq!(signal(requester, "iron-plate"), Some(-6000));
+        assert_eq!(signal(requester, "cybersyn-request-threshold"), Some(2000));
+        assert_eq!(signal(provider, "cybersyn-request-threshold"), Some(2000));
+
+        let (_, train) = train();
+        let blueprint = dispatcher_blueprint(Dispatcher::Cybersyn, &train, &stations);
+        assert_eq!(blueprint.entities.len(), 2);
+    }
+
+    #[test]
+    fn large_train_limits_hold_at_the_largest_signal() {
+        let stations = settings(Dispatcher::Ltn, MAX_TRAIN_LIMIT);
+        assert_eq!(signal(&stations[0], "ltn-max-trains"), Some(i32::MAX));
+        assert_eq!(signal(&stations[0], "iron-plate"), Some(-i32::MAX));
+    }
+}
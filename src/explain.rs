@@ -0,0 +1,318 @@
+use crate::{
+    allocation::Basis,
+    rational::Rational,
+    report::{Report, Row, Section},
+    wagon::WAGON_INSERTERS,
+    IngredientRatio,
+};
+
+/// Format a number with up to three decimal places, leaving out trailing zeros (e.g. "12", "12.5" or "0.333").
+fn number(value: f64) -> String {
+    let formatted = format!("{:.3}", value);
+    match formatted.contains('.') {
+        true => formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string(),
+        false => formatted,
+    }
+}
+
+/// The ordinal of a rank starting from 1 (e.g. "1st", "2nd" or "11th").
+fn ordinal(rank: usize) -> String {
+    let suffix = match (rank % 10, rank % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{rank}{suffix}")
+}
+
+/// Print the totals the ratios are calculated from, and each ingredient's share of them.
+fn print_totals(item_ratios: &[IngredientRatio]) {
+    let total_items: f64 = item_ratios
+        .iter()
+        .map(|item| item.ingredient.amount.to_f64())
+        .sum();
+    let stacks = |item: &IngredientRatio| {
+        item.ingredient.amount.to_f64() / item.ingredient.stack_size as f64
+    };
+    let total_stacks: f64 = item_ratios.iter().map(stacks).sum();
+
+    println!("{:-^80}", " TOTALS ");
+    for item in item_ratios {
+        println!(
+            "{:>40} ---- {} items / {} per stack = {} stacks",
+            item.ingredient.name,
+            item.ingredient.amount,
+            item.ingredient.stack_size,
+            number(stacks(item))
+        );
+    }
+    println!(
+        "{:>40} ---- {} items, {} stacks",
+        "TOTAL",
+        number(total_items),
+        number(total_stacks)
+    );
+
+    println!("{:-^80}", " RATIOS ");
+    for item in item_ratios {
+        println!(
+            "{:>40} ---- ratio {} / {} = {:.2}%, stack ratio {} / {} = {:.2}%",
+            item.ingredient.name,
+            item.ingredient.amount,
+            number(total_items),
+            item.ratio * 100.0,
+            number(stacks(item)),
+            number(total_stacks),
+            item.stack_ratio * 100.0
+        );
+        if let Some(exact) = &item.exact {
+            println!(
+                "{:>40} ---- exactly {} and {}",
+                "", exact.ratio, exact.stack_ratio
+            );
+        }
+    }
+}
+
+/// How one ingredient's quota of a row was rounded.
+struct Rounding {
+    /// The unrounded quota, as printed.
+    quota: String,
+    /// The whole part of the quota.
+    floor: u32,
+    /// The fractional part of the quota, as printed.
+    remainder: String,
+    /// Whether the quota has a fractional part.
+    fractional: bool,
+}
+
+/// Work out how each ingredient's quota of a row was rounded, from the exact quotas if the slots were apportioned exactly (so that the rounding always matches the counts), or the floating point quotas otherwise.
+///
+/// # Returns
+/// The rounding of each ingredient, and the order of the ingredients by largest remainder first, with ties going to the ingredient given first.
+fn roundings(item_ratios: &[IngredientRatio], row: &Row) -> (Vec<Rounding>, Vec<usize>) {
+    let mut order: Vec<usize> = (0..item_ratios.len()).collect();
+
+    if let Some(quotas) = row.allocation.exact_quotas(item_ratios) {
+        order.sort_by(|&a, &b| quotas[b].fract().cmp(&quotas[a].fract()));
+        let roundings = quotas
+            .iter()
+            .map(|quota| Rounding {
+                quota: quota.to_string(),
+                floor: quota.floor() as u32,
+                remainder: quota.fract().to_string(),
+                fractional: quota.fract() > Rational::ZERO,
+            })
+            .collect();
+        return (roundings, order);
+    }
+
+    let quotas = row.allocation.quotas(item_ratios);
+    order.sort_by(|&a, &b| {
+        (quotas[b] - quotas[b].floor()).total_cmp(&(quotas[a] - quotas[a].floor()))
+    });
+    let roundings = quotas
+        .iter()
+        .map(|quota| Rounding {
+            quota: number(*quota),
+            floor: quota.floor() as u32,
+            remainder: number(quota - quota.floor()),
+            fractional: quota - quota.floor() > 0.0,
+        })
+        .collect();
+    (roundings, order)
+}
+
+/// Print how the inserters of a row were handed out, wagon by wagon.
+fn print_inserter_row(item_ratios: &[IngredientRatio], row: &Row) {
+    let allocation = &row.allocation;
+    println!(
+        "{:-^80}",
+        format!(" {}: {} ", row.section.title(), row.label)
+    );
+    println!(
+        "{:>40} ---- {} inserters per car, one for each item the car holds and the rest by the number of each it holds, over {} cars",
+        "APPORTIONED",
+        WAGON_INSERTERS,
+        allocation.slots / WAGON_INSERTERS
+    );
+    for (item, count) in item_ratios.iter().zip(&allocation.counts) {
+        println!("{:>40} ---- {} inserters", item.ingredient.name, count);
+    }
+}
+
+/// Print how the slots of a row were apportioned: the unrounded quota of each ingredient, its whole part, and which ingredients were given one of the slots left over, by largest remainder. Inserters are handed out wagon by wagon instead (see [print_inserter_row]).
+fn print_row(item_ratios: &[IngredientRatio], row: &Row) {
+    if row.section == Section::Inserters {
+        return print_inserter_row(item_ratios, row);
+    }
+
+    let allocation = &row.allocation;
+    let group_slots = allocation.slots / allocation.groups;
+    let (roundings, order) = roundings(item_ratios, row);
+    let leftover =
+        group_slots.saturating_sub(roundings.iter().map(|rounding| rounding.floor).sum());
+
+    println!(
+        "{:-^80}",
+        format!(" {}: {} ", row.section.title(), row.label)
+    );
+    let ratio = match allocation.basis {
+        Basis::Stacks => "stack ratio",
+        Basis::Items => "item ratio",
+    };
+    match allocation.groups {
+        1 => println!(
+            "{:>40} ---- {} slots by {}, {} left over after rounding down",
+            "APPORTIONED", group_slots, ratio, leftover
+        ),
+        groups => println!(
+            "{:>40} ---- {} slots per car by {}, {} left over after rounding down, then x{} cars",
+            "APPORTIONED", group_slots, ratio, leftover, groups
+        ),
+    }
+
+    for (index, (item, rounding)) in item_ratios.iter().zip(&roundings).enumerate() {
+        let count = allocation.counts[index] / allocation.groups;
+        let rank = order.iter().position(|other| *other == index).unwrap_or(0) + 1;
+        let share = match allocation.basis {
+            Basis::Stacks => item.stack_ratio,
+            Basis::Items => item.ratio,
+        };
+
+        let decision = if count > rounding.floor {
+            format!(
+                "rounded up, {} largest remainder {}",
+                ordinal(rank),
+                rounding.remainder
+            )
+        } else if rounding.fractional {
+            format!(
+                "rounded down, {} largest remainder {}",
+                ordinal(rank),
+                rounding.remainder
+            )
+        } else {
+            "whole".to_string()
+        };
+        let total = match allocation.groups {
+            1 => String::new(),
+            groups => format!(" x{} = {}", groups, allocation.counts[index]),
+        };
+
+        println!(
+            "{:>40} ---- {:.2}% x {} = {} -> {} ({}){}",
+            item.ingredient.name,
+            share * 100.0,
+            group_slots,
+            rounding.quota,
+            count,
+            decision,
+            total
+        );
+    }
+}
+
+/// Print every step of the calculation: the totals the ratios are calculated from, each ingredient's ratios, and how the slots of every row of the report were apportioned and rounded.
+///
+/// # Parameters
+/// * `item_ratios` - The ingredients the report was calculated over.
+/// * `report` - The report whose rows to explain.
+pub fn print_explanation(item_ratios: &[IngredientRatio], report: &Report) {
+    println!("{:=^80}", " EXPLANATION ");
+    print_totals(item_ratios);
+    for row in &report.rows {
+        print_row(item_ratios, row);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{calculate_ratios, report::Selection, Ingredient};
+
+    /// The report of a single car over ingredients of the given amounts, all with a stack size of 100.
+    fn report(amounts: &[i128], exact: bool) -> (Vec<IngredientRatio>, Report) {
+        let ingredients: Vec<Ingredient> = amounts
+            .iter()
+            .enumerate()
+            .map(|(index, amount)| {
+                Ingredient::new(
+                    &format!("item {index}"),
+                    Rational::from_integer(*amount),
+                    100,
+                )
+                .unwrap()
+            })
+            .collect();
+        let item_ratios = calculate_ratios(&ingredients, exact).unwrap();
+        let selection = Selection {
+            cars: vec![1],
+            ..Selection::default()
+        };
+        let report = Report::new(&item_ratios, &selection, false);
+        (item_ratios, report)
+    }
+
+    #[test]
+    fn formats_numbers_and_ordinals() {
+        assert_eq!(number(12.0), "12");
+        assert_eq!(number(12.5), "12.5");
+        assert_eq!(number(1.0 / 3.0), "0.333");
+        assert_eq!(
+            [1, 2, 3, 4, 11, 12, 13, 21, 22, 101, 111].map(ordinal),
+            [
+                "1st", "2nd", "3rd", "4th", "11th", "12th", "13th", "21st", "22nd", "101st",
+                "111th"
+            ]
+        );
+    }
+
+    #[test]
+    fn exact_rounding_matches_the_exact_counts() {
+        // In floating point, 1 of 5 stacks of 40 slots is a quota of 7.999..., which would read as rounded up.
+        let (item_ratios, report) = report(&[1, 1, 3], true);
+        let row = &report.rows[0];
+        assert_eq!(row.allocation.counts, vec![8, 8, 24]);
+        assert!(row.allocation.quotas(&item_ratios)[0] < 8.0);
+
+        let (roundings, _) = roundings(&item_ratios, row);
+        let floors: Vec<u32> = roundings.iter().map(|rounding| rounding.floor).collect();
+        assert_eq!(floors, vec![8, 8, 24]);
+        assert!(roundings.iter().all(|rounding| !rounding.fractional));
+        assert_eq!(roundings[2].quota, "24");
+    }
+
+    #[test]
+    fn rounding_ranks_follow_the_counts() {
+        for exact in [false, true] {
+            for amounts in [[1, 1, 1], [1, 2, 4], [7, 11, 13], [1, 1, 3]] {
+                let (item_ratios, report) = report(&amounts, exact);
+                for row in report
+                    .rows
+                    .iter()
+                    .filter(|row| row.section != Section::Inserters)
+                {
+                    let (roundings, order) = roundings(&item_ratios, row);
+                    let slots = row.allocation.slots / row.allocation.groups;
+                    let leftover =
+                        slots - roundings.iter().map(|rounding| rounding.floor).sum::<u32>();
+                    for (rank, index) in order.iter().enumerate() {
+                        let rounded_up = (rank as u32) < leftover;
+                        assert_eq!(
+                            row.allocation.counts[*index] / row.allocation.groups,
+                            roundings[*index].floor + u32::from(rounded_up),
+                            "{amounts:?} {} exact {exact}",
+                            row.label
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
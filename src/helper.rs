@@ -0,0 +1,186 @@
+use std::borrow::Cow;
+
+use rustyline::{
+    completion::{Completer, Pair},
+    highlight::Highlighter,
+    hint::{Hint, Hinter},
+    validate::Validator,
+    Context, Helper,
+};
+
+use crate::data;
+
+/// ANSI escape code dimming hints.
+const DIM: &str = "\x1b[2m";
+
+/// ANSI escape code marking unknown items in red.
+const RED: &str = "\x1b[31m";
+
+/// ANSI escape code resetting the style.
+const RESET: &str = "\x1b[0m";
+
+/// Rustyline helper for typing item names: completes names from the item catalog, hints the rest of the name and its stack size, and marks names of unknown items. It only acts while [ItemHelper::active] is set, so that other prompts (such as amounts) are left alone.
+#[derive(Debug, Default)]
+pub struct ItemHelper {
+    /// Whether an item name is currently being typed.
+    pub active: bool,
+}
+
+/// A hint of the rest of an item name being typed, followed by the item's stack size.
+#[derive(Debug)]
+pub struct ItemHint {
+    /// The text shown after the cursor.
+    display: String,
+    /// The rest of the item name, inserted when the hint is accepted.
+    completion: Option<String>,
+}
+
+impl Hint for ItemHint {
+    fn display(&self) -> &str {
+        self.display.as_str()
+    }
+
+    fn completion(&self) -> Option<&str> {
+        self.completion.as_deref()
+    }
+}
+
+/// The names of known items starting with what has been typed, ignoring case.
+fn matching_names(typed: &str) -> impl Iterator<Item = &'static str> + '_ {
+    let typed = typed.to_lowercase();
+    data::STACK_SIZES
+        .iter()
+        .map(|(name, _)| *name)
+        .filter(move |name| name.starts_with(typed.as_str()))
+}
+
+impl Completer for ItemHelper {
+    type Candidate = Pair;
+
+    /// Complete the whole line as an item name, as item names can contain spaces.
+    fn complete(
+        &self,
+        line: &str,
+        _pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        if !self.active {
+            return Ok((0, Vec::new()));
+        }
+
+        let candidates = matching_names(line)
+            .map(|name| Pair {
+                display: name.to_string(),
+                replacement: name.to_string(),
+            })
+            .collect();
+        Ok((0, candidates))
+    }
+}
+
+impl Hinter for ItemHelper {
+    type Hint = ItemHint;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<ItemHint> {
+        if !self.active || line.is_empty() || pos < line.len() {
+            return None;
+        }
+
+        // Hint the exact item if it is known, or else the first item it could be the start of.
+        let lowercase = line.to_lowercase();
+        let name = match data::fetch_item_stack_size(lowercase.as_str()) {
+            Some(_) => lowercase.as_str(),
+            None => matching_names(line).next()?,
+        };
+        let stack_size = data::fetch_item_stack_size(name)?;
+        let rest = name.get(line.len()..).unwrap_or_default();
+
+        Some(ItemHint {
+            display: format!("{} (stack size {})", rest, stack_size),
+            completion: Some(rest.to_string()).filter(|rest| !rest.is_empty()),
+        })
+    }
+}
+
+impl Highlighter for ItemHelper {
+    /// Mark the line in red if it is not the name, or the start of the name, of any known item.
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        if self.active && !line.is_empty() && matching_names(line).next().is_none() {
+            Cow::Owned(format!("{RED}{line}{RESET}"))
+        } else {
+            Cow::Borrowed(line)
+        }
+    }
+
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(format!("{DIM}{hint}{RESET}"))
+    }
+
+    /// Highlight the line again on every keypress while typing an item name, as any character can make it known or unknown.
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        self.active
+    }
+}
+
+impl Validator for ItemHelper {}
+
+impl Helper for ItemHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustyline::history::DefaultHistory;
+
+    /// A helper typing an item name.
+    fn active() -> ItemHelper {
+        ItemHelper { active: true }
+    }
+
+    #[test]
+    fn completes_item_names_ignoring_case() {
+        let history = DefaultHistory::new();
+        let ctx = Context::new(&history);
+
+        let (start, candidates) = active().complete("Iron G", 6, &ctx).unwrap();
+        assert_eq!(start, 0);
+        let names: Vec<&str> = candidates
+            .iter()
+            .map(|pair| pair.replacement.as_str())
+            .collect();
+        assert_eq!(names, ["iron gear wheel"]);
+
+        let (_, candidates) = ItemHelper::default().complete("iron", 4, &ctx).unwrap();
+        assert!(candidates.is_empty());
+    }
+
+    #[test]
+    fn hints_the_rest_of_the_name_and_its_stack_size() {
+        let history = DefaultHistory::new();
+        let ctx = Context::new(&history);
+
+        let hint = active().hint("iron gear", 9, &ctx).unwrap();
+        assert_eq!(hint.display(), " wheel (stack size 100)");
+        assert_eq!(hint.completion(), Some(" wheel"));
+
+        let hint = active().hint("iron plate", 10, &ctx).unwrap();
+        assert_eq!(hint.display(), " (stack size 100)");
+        assert_eq!(hint.completion(), None);
+
+        assert!(active().hint("iron gear", 4, &ctx).is_none());
+        assert!(active().hint("unobtainium", 11, &ctx).is_none());
+        assert!(ItemHelper::default().hint("iron gear", 9, &ctx).is_none());
+    }
+
+    #[test]
+    fn marks_unknown_items_in_red() {
+        assert_eq!(active().highlight("iron", 4), "iron");
+        assert_eq!(
+            active().highlight("unobtainium", 11),
+            format!("{RED}unobtainium{RESET}")
+        );
+        assert_eq!(
+            ItemHelper::default().highlight("unobtainium", 11),
+            "unobtainium"
+        );
+    }
+}
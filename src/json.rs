@@ -0,0 +1,405 @@
+use serde::Serialize;
+
+use crate::{
+    allocation::{Allocation, Basis},
+    args::Settings,
+    compare::Comparison,
+    rational::Rational,
+    report::{Chest, Report, Section, Verbosity},
+    wagon::{self, TrainFilters},
+    IngredientRatio,
+};
+
+/// The version of the JSON document's schema. This is raised whenever a field is renamed, removed or changes meaning; new fields may be added without raising it.
+const SCHEMA_VERSION: u32 = 1;
+
+/// Everything calculated, as printed by "--format json". Ingredients are referred to by their index into `ingredients` throughout.
+#[derive(Debug, Serialize)]
+pub struct Document {
+    /// The version of this schema.
+    pub schema_version: u32,
+    /// The settings calculated over.
+    pub settings: DocumentSettings,
+    /// The ingredients calculated over, in the order they were given.
+    pub ingredients: Vec<DocumentIngredient>,
+    /// Every configuration calculated over (trains, chests and inserters), in the order they are printed as text.
+    pub configurations: Vec<DocumentConfiguration>,
+    /// The planned filters of every selected train.
+    pub trains: Vec<DocumentTrain>,
+    /// The time taken to unload each selected train through its inserters.
+    pub timings: Vec<DocumentTiming>,
+    /// The comparison of train lengths, if comparing train lengths.
+    pub comparison: Option<DocumentComparison>,
+}
+
+/// The settings a [Document] was calculated over.
+#[derive(Debug, Serialize)]
+pub struct DocumentSettings {
+    /// The cargo wagon counts of the selected trains.
+    pub cars: Vec<u32>,
+    /// Whether "SC" (same-car) filtering was calculated.
+    pub same_car: bool,
+    /// Whether "DC" (different-car) filtering was calculated.
+    pub different_car: bool,
+    /// Whether whole wagons were dedicated to a single ingredient where it fills them.
+    pub pure: bool,
+    /// Whether ratios were calculated with exact fractions.
+    pub exact: bool,
+    /// The type of chest the train is unloaded into.
+    pub chest: Chest,
+    /// The number of slots of the chest.
+    pub chest_slots: u32,
+    /// The verbosity of the text output. Every field is always present in the JSON output regardless.
+    pub verbosity: Verbosity,
+}
+
+/// An ingredient of a [Document] and its ratios.
+#[derive(Debug, Serialize)]
+pub struct DocumentIngredient {
+    /// The index of the ingredient.
+    pub index: usize,
+    /// The symbol of the ingredient in filter grids (e.g. "A").
+    pub symbol: char,
+    /// The name of the ingredient.
+    pub name: String,
+    /// The amount of the ingredient needed, exactly as given.
+    pub amount: Rational,
+    /// The number of items in one stack of the ingredient.
+    pub stack_size: u32,
+    /// The fraction of all items that are this ingredient.
+    pub ratio: f64,
+    /// The fraction of all stacks that are this ingredient.
+    pub stack_ratio: f64,
+    /// The exact fraction of all items that are this ingredient, if calculated exactly.
+    pub exact_ratio: Option<Rational>,
+    /// The exact fraction of all stacks that are this ingredient, if calculated exactly.
+    pub exact_stack_ratio: Option<Rational>,
+}
+
+/// A configuration of a [Document], such as a train or a row of chests, and the slots of it handed out between ingredients.
+#[derive(Debug, Serialize)]
+pub struct DocumentConfiguration {
+    /// The section of the output the configuration belongs in: "stacks", "items", "chests" or "inserters".
+    pub section: Section,
+    /// The label of the configuration (e.g. "80 SLOTS (DC 2 cars)").
+    pub label: String,
+    /// The unit `values` are counted in: "stacks", "items" or "inserters".
+    pub unit: &'static str,
+    /// The details of the slots handed out.
+    #[serde(flatten)]
+    pub allocation: DocumentAllocation,
+    /// The value of each ingredient, counted in `unit`.
+    pub values: Vec<u64>,
+}
+
+/// The slots of a configuration handed out between ingredients, along with how closely they deliver the requested ratio.
+#[derive(Debug, Serialize)]
+pub struct DocumentAllocation {
+    /// The total number of slots handed out.
+    pub slots: u32,
+    /// What each slot holds: "stacks" (one full stack) or "items" (an equal share of items, such as an inserter).
+    pub basis: Basis,
+    /// The number of slots given to each ingredient.
+    pub counts: Vec<u32>,
+    /// The fraction of all items delivered that are each ingredient.
+    pub delivered_ratios: Vec<f64>,
+    /// The delivered ratio of each ingredient minus its requested ratio.
+    pub ratio_deviations: Vec<f64>,
+    /// The deviation of each ingredient relative to its requested ratio.
+    pub ratio_errors: Vec<f64>,
+    /// The largest of `ratio_errors`.
+    pub max_ratio_error: f64,
+    /// The number of slots left over once the limiting ingredient runs out in the requested ratio.
+    pub wasted_slots: f64,
+    /// The index of the ingredient that runs out first in the requested ratio, if any.
+    pub limiting_ingredient: Option<usize>,
+}
+
+/// The planned filters of every wagon of one train of a [Document].
+#[derive(Debug, Serialize)]
+pub struct DocumentTrain {
+    /// The number of cargo wagons on the train.
+    pub cars: u32,
+    /// Whether each wagon follows the same filter ("SC" filtering).
+    pub same_car: bool,
+    /// The wagons of the train, from the front of the train to the back.
+    pub wagons: Vec<DocumentWagon>,
+}
+
+/// The planned filter of one wagon of a [DocumentTrain].
+#[derive(Debug, Serialize)]
+pub struct DocumentWagon {
+    /// Whether the wagon holds only a single ingredient.
+    pub pure: bool,
+    /// The index of the ingredient each slot is filtered to, or null if the slot is left unfiltered.
+    pub slots: Vec<Option<usize>>,
+}
+
+/// The time taken to unload a train of a [Document] through its inserters.
+#[derive(Debug, Serialize)]
+pub struct DocumentTiming {
+    /// The label of the train and its inserters (e.g. "27 INSERTERS (DC 4 cars)").
+    pub label: String,
+    /// The number of items of each ingredient on the train.
+    pub items: Vec<u64>,
+    /// The number of inserters unloading each ingredient.
+    pub inserters: Vec<u32>,
+    /// The number of seconds taken to unload each ingredient, or null if no inserters unload it.
+    pub seconds: Vec<Option<f64>>,
+}
+
+/// The comparison of train lengths of a [Document].
+#[derive(Debug, Serialize)]
+pub struct DocumentComparison {
+    /// Whether each wagon follows the same filter ("SC" filtering).
+    pub same_car: bool,
+    /// The largest acceptable relative ratio error (e.g. 0.05 for 5%).
+    pub target_error: f64,
+    /// The cargo wagon count of the smallest train within the target ratio error, if any.
+    pub best: Option<u32>,
+    /// The allocation of each train length compared.
+    pub trains: Vec<DocumentComparedTrain>,
+}
+
+/// One train length of a [DocumentComparison].
+#[derive(Debug, Serialize)]
+pub struct DocumentComparedTrain {
+    /// The number of cargo wagons on the train.
+    pub cars: u32,
+    /// The details of the slots handed out.
+    #[serde(flatten)]
+    pub allocation: DocumentAllocation,
+}
+
+impl Document {
+    /// Gather everything calculated for the given settings into a document.
+    ///
+    /// # Parameters
+    /// * `item_ratios` - The ingredients to calculate over.
+    /// * `settings` - The settings to calculate with.
+    pub fn new(item_ratios: &[IngredientRatio], settings: &Settings) -> Self {
+        let selection = &settings.selection;
+        let report = Report::new(item_ratios, selection, settings.pure);
+
+        let comparison = settings.compare.clone().map(|range| {
+            let same_car = selection.same_car && !selection.different_car;
+            DocumentComparison::new(
+                item_ratios,
+                &Comparison::new(item_ratios, range, same_car, settings.target_error),
+            )
+        });
+
+        Document {
+            schema_version: SCHEMA_VERSION,
+            settings: DocumentSettings {
+                cars: selection.cars.clone(),
+                same_car: selection.same_car,
+                different_car: selection.different_car,
+                pure: settings.pure,
+                exact: settings.exact,
+                chest: selection.chest,
+                chest_slots: selection.chest.slots(),
+                verbosity: settings.verbosity,
+            },
+            ingredients: item_ratios
+                .iter()
+                .enumerate()
+                .map(|(index, item)| DocumentIngredient {
+                    index,
+                    symbol: wagon::slot_symbol(index),
+                    name: item.ingredient.name.clone(),
+                    amount: item.ingredient.amount,
+                    stack_size: item.ingredient.stack_size,
+                    ratio: item.ratio,
+                    stack_ratio: item.stack_ratio,
+                    exact_ratio: item.exact.as_ref().map(|exact| exact.ratio),
+                    exact_stack_ratio: item.exact.as_ref().map(|exact| exact.stack_ratio),
+                })
+                .collect(),
+            configurations: report
+                .rows
+                .iter()
+                .map(|row| DocumentConfiguration {
+                    section: row.section,
+                    label: row.label.clone(),
+                    unit: row.section.unit(),
+                    allocation: DocumentAllocation::new(item_ratios, &row.allocation),
+                    values: (0..item_ratios.len())
+                        .map(|index| row.value(item_ratios, index))
+                        .collect(),
+                })
+                .collect(),
+            trains: wagon::plan_trains(item_ratios, selection, settings.pure)
+                .iter()
+                .map(DocumentTrain::new)
+                .collect(),
+            timings: report
+                .timings
+                .iter()
+                .map(|timing| DocumentTiming {
+                    label: timing.label.clone(),
+                    items: timing.items.clone(),
+                    inserters: timing.inserters.clone(),
+                    seconds: (0..item_ratios.len())
+                        .map(|index| timing.seconds(index))
+                        .collect(),
+                })
+                .collect(),
+            comparison,
+        }
+    }
+}
+
+impl DocumentAllocation {
+    /// Describe an allocation and how closely it delivers the requested ratio.
+    fn new(item_ratios: &[IngredientRatio], allocation: &Allocation) -> Self {
+        DocumentAllocation {
+            slots: allocation.slots,
+            basis: allocation.basis,
+            counts: allocation.counts.clone(),
+            delivered_ratios: allocation.delivered_ratios(item_ratios),
+            ratio_deviations: allocation.ratio_deviations(item_ratios),
+            ratio_errors: allocation.ratio_errors(item_ratios),
+            max_ratio_error: allocation.max_ratio_error(item_ratios),
+            wasted_slots: allocation.wasted_slots(item_ratios),
+            limiting_ingredient: allocation.limiting_ingredient(item_ratios),
+        }
+    }
+}
+
+impl DocumentTrain {
+    /// Describe the planned filters of a train.
+    fn new(train: &TrainFilters) -> Self {
+        DocumentTrain {
+            cars: train.cars,
+            same_car: train.same_car,
+            wagons: train
+                .wagons
+                .iter()
+                .map(|wagon| DocumentWagon {
+                    pure: wagon.is_pure(),
+                    slots: wagon.slots.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl DocumentComparison {
+    /// Describe a comparison of train lengths.
+    fn new(item_ratios: &[IngredientRatio], comparison: &Comparison) -> Self {
+        DocumentComparison {
+            same_car: comparison.same_car,
+            target_error: comparison.target_error,
+            best: comparison.best,
+            trains: comparison
+                .allocations
+                .iter()
+                .map(|(cars, allocation)| DocumentComparedTrain {
+                    cars: *cars,
+                    allocation: DocumentAllocation::new(item_ratios, allocation),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Print everything calculated for the given settings as a JSON document.
+///
+/// # Parameters
+/// * `item_ratios` - The ingredients to calculate over.
+/// * `settings` - The settings to calculate with.
+pub fn print_json(item_ratios: &[IngredientRatio], settings: &Settings) {
+    let document = Document::new(item_ratios, settings);
+    match serde_json::to_string_pretty(&document) {
+        Ok(json) => println!("{json}"),
+        Err(e) => {
+            eprintln!("Could not write JSON output: {e}");
+            std::process::exit(crate::EXIT_IO_ERROR);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{args::Args, calculate_ratios, plan::Plan};
+    use clap::Parser;
+
+    /// The JSON document of the given arguments, as it is printed.
+    fn document(args: &[&str]) -> serde_json::Value {
+        let args =
+            Args::try_parse_from(std::iter::once("factrain").chain(args.iter().copied())).unwrap();
+        let settings = Settings::new(args, &Plan::default()).unwrap();
+        let item_ratios = calculate_ratios(&settings.ingredients, settings.exact).unwrap();
+        serde_json::to_value(Document::new(&item_ratios, &settings)).unwrap()
+    }
+
+    #[test]
+    fn documents_every_calculation() {
+        let document = document(&[
+            "--cars",
+            "2",
+            "--dc",
+            "--format",
+            "json",
+            "iron plate=1200",
+            "steel=300:50",
+        ]);
+        assert_eq!(document["schema_version"], SCHEMA_VERSION);
+        assert_eq!(document["settings"]["cars"], serde_json::json!([2]));
+        assert_eq!(document["settings"]["chest"], "steel");
+        assert_eq!(document["settings"]["chest_slots"], 48);
+
+        let ingredients = document["ingredients"].as_array().unwrap();
+        assert_eq!(ingredients.len(), 2);
+        assert_eq!(ingredients[1]["name"], "steel plate");
+        assert_eq!(ingredients[1]["amount"], 300);
+        assert_eq!(ingredients[1]["stack_size"], 50);
+        assert!(ingredients[1]["exact_ratio"].is_null());
+
+        let configurations = document["configurations"].as_array().unwrap();
+        let sections: Vec<&str> = configurations
+            .iter()
+            .map(|configuration| configuration["section"].as_str().unwrap())
+            .collect();
+        assert_eq!(sections, vec!["stacks", "items", "chests", "inserters"]);
+        for configuration in configurations {
+            let counts = configuration["counts"].as_array().unwrap();
+            let slots: u64 = counts.iter().map(|count| count.as_u64().unwrap()).sum();
+            assert_eq!(configuration["slots"], slots);
+            assert_eq!(configuration["values"].as_array().unwrap().len(), 2);
+        }
+
+        let wagons = document["trains"][0]["wagons"].as_array().unwrap();
+        assert_eq!(wagons.len(), 2);
+        assert_eq!(wagons[0]["slots"].as_array().unwrap().len(), 40);
+        assert_eq!(document["timings"].as_array().unwrap().len(), 1);
+        assert!(document["comparison"].is_null());
+    }
+
+    #[test]
+    fn writes_exact_fractions_as_strings() {
+        let document = document(&[
+            "--cars",
+            "1",
+            "--exact",
+            "iron plate=100",
+            "copper plate=200",
+        ]);
+        assert_eq!(document["ingredients"][0]["exact_ratio"], "1/3");
+        assert_eq!(document["ingredients"][1]["exact_stack_ratio"], "2/3");
+    }
+
+    #[test]
+    fn documents_comparisons() {
+        let document = document(&["--compare=1-3", "iron plate=100", "copper plate=200"]);
+        let trains = document["comparison"]["trains"].as_array().unwrap();
+        let cars: Vec<u64> = trains
+            .iter()
+            .map(|train| train["cars"].as_u64().unwrap())
+            .collect();
+        assert_eq!(cars, vec![1, 2, 3]);
+        assert!(trains[0]["max_ratio_error"].is_number());
+    }
+}
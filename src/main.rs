@@ -1,401 +1,424 @@
+mod allocation;
+mod amount;
+mod args;
+mod audit;
+mod blueprint;
+mod compare;
 mod data;
-
-use std::{fmt::Display, str::FromStr};
+mod dispatch;
+mod explain;
+mod helper;
+mod json;
+mod plan;
+mod rational;
+mod report;
+mod schedule;
+mod session;
+mod table;
+mod tui;
+mod wagon;
+
+use std::{
+    fmt::Display,
+    io::{BufRead, IsTerminal},
+    str::FromStr,
+};
+
+use rational::Rational;
+
+/// Rustyline editor used to read user input lines, completing item names.
+type LineEditor = rustyline::Editor<helper::ItemHelper, rustyline::history::FileHistory>;
+
+/// Exit code for invalid input, such as a malformed option or ingredient.
+const EXIT_INVALID_INPUT: i32 = 1;
+
+/// Exit code for errors reading input or writing files.
+const EXIT_IO_ERROR: i32 = 2;
+
+/// The largest stack size accepted, well above any item's in the game or its mods, so that slot counts multiplied by stack sizes stay within range.
+const MAX_STACK_SIZE: u32 = 1_000_000;
 
 fn main() {
-    // Read environment variables.
-    let env_args: Vec<String> = std::env::args().collect();
-
     #[cfg(debug_assertions)]
     {
-        println!("DEBUG main()[env_args] {:?}", env_args);
-    }
-
-    let help_text = format!(
-            "USAGE\n    {}\n\nOPTIONS\n    {:<16}{}\n    {:<16}{}\n    {:<16}{}\n    {:<16}{}\n    {:<16}{}\n    {:<16}{}\n    {:<16}{}\n    {:<16}{}",
-            "[sc/dc] [1-car/2-car/3-car/4-car] (extra)\n\n    Options can be passed in any order. You can combine any options together for additional output, as none of them are mutually exclusive.",
-            "help",
-            "Print this help output.",
-            "1-car",
-            "One cargo wagon per train.",
-            "2-car",
-            "Two cargo wagons per train.",
-            "3-car",
-            "Three cargo wagons per train.",
-            "4-car",
-            "Four cargo wagons per train.",
-            "sc",
-            "Same-car wagon filtering. Use this option to restrict each wagon (on one train) to same filter.",
-            "dc",
-            "Different-car wagon filtering. Use this option if you wish for each wagon (on one train) to be free to choose its own individual filter.",
-            "extra",
-            "Print some extras."
-        );
+        let env_args: Vec<String> = std::env::args().collect();
+        eprintln!("DEBUG main()[env_args] {:?}", env_args);
+    }
+
+    // Parse the arguments, printing help or version information instead if requested.
+    let args = match args::Args::parse_env() {
+        Ok(args) => args,
+        Err(e) => {
+            let _ = e.print();
+            std::process::exit(if e.use_stderr() {
+                EXIT_INVALID_INPUT
+            } else {
+                0
+            });
+        }
+    };
+
+    // Plan file to read ingredients and options from, if given.
+    let plan = match &args.plan {
+        Some(path) => match plan::Plan::load(path) {
+            Ok(plan) => plan,
+            Err(e) => {
+                println!("{e}");
+                std::process::exit(EXIT_INVALID_INPUT);
+            }
+        },
+        None => plan::Plan::default(),
+    };
 
-    // Print help information if requested.
-    if env_args.contains(&"help".into()) {
-        println!("{help_text}");
+    // Audit a blueprint against the plan instead of calculating ratios, if requested.
+    if let Some(args::Command::Audit { blueprint }) = &args.command {
+        run_audit(blueprint, &plan);
         return;
     }
 
-    if env_args.len() == 1 {
-        println!("You have not passed in any options. Pass options in following the name of the program as you execute it in your terminal (e.g. \"./factrain sc 4-car\" on Linux or \".\\factrain.exe sc 4-car\" on Windows).\n");
-        println!("{help_text}");
+    // Design the station in a full-screen terminal UI instead of printing a report, if requested.
+    if let Some(args::Command::Tui) = &args.command {
+        run_tui(args, &plan);
         return;
     }
 
-    // Initialize Rustyline Editor in order to read user input lines.
-    let mut rl_editor = rustyline::DefaultEditor::new().unwrap();
+    // Options given on the command line are added to those given in the plan.
+    let settings = match args::Settings::new(args, &plan) {
+        Ok(settings) => settings,
+        Err(e) => {
+            println!("{e}");
+            std::process::exit(EXIT_INVALID_INPUT);
+        }
+    };
 
-    // Prompt user for items to be calculated.
-    let ingredients = prompt_ingredients(&mut rl_editor);
+    let mut ingredients = settings.ingredients.clone();
 
-    #[cfg(debug_assertions)]
-    {
-        println!("DEBUG main()[items]: {:?}", ingredients);
-    }
+    if ingredients.is_empty() {
+        if std::io::stdin().is_terminal() {
+            // Initialize Rustyline Editor in order to read user input lines.
+            let mut rl_editor = LineEditor::new().unwrap();
+            rl_editor.set_helper(Some(helper::ItemHelper::default()));
 
-    // Total number of items specified in user-given amounts.
-    let mut total_items = 0.0;
-    for item in &ingredients {
-        total_items += item.amount;
-    }
+            // Recall the input history of earlier runs. There is none on the first run.
+            if let Some(path) = session::history_path() {
+                let _ = rl_editor.load_history(&path);
+            }
 
-    // Total item stacks specified in user-given amounts.
-    let mut total_stacks = 0.0;
-    for item in &ingredients {
-        total_stacks += item.amount / item.stack_size as f64;
+            // Prompt user for items to be calculated, then keep calculating over them in an interactive session.
+            prompt_ingredients(&mut rl_editor, &mut ingredients);
+            session::run_session(&mut rl_editor, settings, ingredients);
+            return;
+        } else {
+            // Read items to be calculated line by line when input is piped in.
+            ingredients = read_ingredients(std::io::stdin().lock());
+        }
     }
 
-    #[cfg(debug_assertions)]
-    {
-        println!("DEBUG main()[total_stacks]: {:?}", total_stacks);
+    if ingredients.is_empty() {
+        println!("No ingredients were given.");
+        return;
     }
 
-    // Calculate ingredient ratios.
-    let mut item_ratios: Vec<IngredientRatio> = Vec::new();
-    for item in &ingredients {
-        item_ratios.push(IngredientRatio {
-            ingredient: item.clone(),
-            ratio: item.amount / total_items,
-            stack_ratio: (item.amount / item.stack_size as f64) / total_stacks,
-        });
+    // Save the plan as calculated, if requested.
+    if let Err(e) = save_plan(&settings, &ingredients) {
+        println!("{e}");
+        std::process::exit(EXIT_IO_ERROR);
     }
 
-    #[cfg(debug_assertions)]
-    {
-        println!("DEBUG main()[item_ratios]: {:?}", item_ratios);
+    if let Err(e) = print_output(&settings, &ingredients) {
+        println!("{e}");
+        std::process::exit(EXIT_INVALID_INPUT);
     }
+}
 
-    // Print calculated output.
-    for item in item_ratios {
-        println!(
-            "{:=^80}",
-            format!(
-                " {} x{}/{} ({:.2}%) ",
-                item.ingredient.name.to_uppercase(),
-                item.ingredient.amount,
-                item.ingredient.stack_size,
-                item.stack_ratio * 100.0
-            )
-        );
-
-        println!("{:=^80}", " STACKS ");
-
-        if env_args.contains(&"1-car".into()) || env_args.contains(&"sc".into()) {
-            println!(
-                "{:>40} ---- {} {} stacks",
-                "40 SLOTS (1 car)",
-                (item.stack_ratio * 40.0).round() as u32,
-                item.ingredient.name
-            );
-        }
-
-        if env_args.contains(&"2-car".into()) && env_args.contains(&"dc".into()) {
-            println!(
-                "{:>40} ---- {} {} stacks",
-                "80 SLOTS (2 cars)",
-                (item.stack_ratio * 80.0).round() as u32,
-                item.ingredient.name
-            );
-        }
-
-        if env_args.contains(&"3-car".into()) && env_args.contains(&"dc".into()) {
-            println!(
-                "{:>40} ---- {} {} stacks",
-                "120 SLOTS (3 cars)",
-                (item.stack_ratio * 120.0).round() as u32,
-                item.ingredient.name
-            );
-        }
-
-        if env_args.contains(&"4-car".into()) && env_args.contains(&"dc".into()) {
-            println!(
-                "{:>40} ---- {} {} stacks",
-                "160 SLOTS (4 cars)",
-                (item.stack_ratio * 160.0).round() as u32,
-                item.ingredient.name
-            );
-        }
+/// Run the full-screen terminal UI, starting from the ingredients and options of the plan and command line, then save the plan as it was left to the plan file given with `--save`, if any.
+fn run_tui(args: args::Args, plan: &plan::Plan) {
+    if !std::io::stdout().is_terminal() {
+        println!("The terminal UI needs to be run in a terminal.");
+        std::process::exit(EXIT_INVALID_INPUT);
+    }
 
-        if env_args.contains(&"extra".into()) {
-            println!(
-                "{:>40} ---- {} {} stacks",
-                "48 SLOTS (1 steel chest)",
-                (item.stack_ratio * 48.0).round() as u32,
-                item.ingredient.name
-            );
+    let settings = match args::Settings::new(args, &tui::tui_plan(plan)) {
+        Ok(settings) => settings,
+        Err(e) => {
+            println!("{e}");
+            std::process::exit(EXIT_INVALID_INPUT);
         }
+    };
 
-        if env_args.contains(&"1-car".into()) && env_args.contains(&"extra".into()) {
-            println!(
-                "{:>40} ---- {} {} stacks",
-                "288 SLOTS (6 steel chests)",
-                (item.stack_ratio * 288.0).round() as u32,
-                item.ingredient.name
-            );
+    let (settings, ingredients) = match tui::run(settings) {
+        Ok(result) => result,
+        Err(e) => {
+            println!("Failed to run the terminal UI: {e}");
+            std::process::exit(EXIT_IO_ERROR);
         }
+    };
 
-        if env_args.contains(&"2-car".into()) && env_args.contains(&"extra".into()) {
-            println!(
-                "{:>40} ---- {} {} stacks",
-                "624 SLOTS (13 steel chests)",
-                (item.stack_ratio * 624.0).round() as u32,
-                item.ingredient.name
-            );
-        }
+    if ingredients.is_empty() {
+        return;
+    }
+    if let Err(e) = save_plan(&settings, &ingredients) {
+        println!("{e}");
+        std::process::exit(EXIT_IO_ERROR);
+    }
+}
 
-        if env_args.contains(&"3-car".into()) && env_args.contains(&"extra".into()) {
-            println!(
-                "{:>40} ---- {} {} stacks",
-                "960 SLOTS (20 steel chests)",
-                (item.stack_ratio * 960.0).round() as u32,
-                item.ingredient.name
-            );
-        }
+/// Save the ingredients and settings calculated over to the plan file given with `--save`, if any.
+///
+/// # Returns
+/// [Ok] if the plan was saved or no plan file was given, or [Err]\(message) if it could not be written.
+fn save_plan(settings: &args::Settings, ingredients: &[Ingredient]) -> Result<(), String> {
+    let Some(path) = &settings.save else {
+        return Ok(());
+    };
+
+    plan::Plan::from_settings(settings, ingredients).save(path)?;
+    eprintln!("Saved plan to \"{}\".", path.display());
+    Ok(())
+}
 
-        if env_args.contains(&"4-car".into()) && env_args.contains(&"extra".into()) {
-            println!(
-                "{:>40} ---- {} {} stacks",
-                "1296 SLOTS (27 steel chests)",
-                (item.stack_ratio * 1296.0).round() as u32,
-                item.ingredient.name
-            );
-        }
+/// Calculate the ratios of the ingredients and print them in the output requested by the settings.
+///
+/// # Parameters
+/// * `settings` - The settings to calculate with, which must have been validated.
+/// * `ingredients` - The ingredients to calculate over.
+///
+/// # Returns
+/// [Ok] if the output was printed, or [Err]\(message) if the ratios of the ingredients cannot be calculated.
+fn print_output(settings: &args::Settings, ingredients: &[Ingredient]) -> Result<(), String> {
+    let selection = &settings.selection;
 
-        println!("{:=^80}", " ITEMS ");
+    #[cfg(debug_assertions)]
+    {
+        eprintln!("DEBUG main()[items]: {:?}", ingredients);
+    }
 
-        if env_args.contains(&"1-car".into()) {
-            println!(
-                "{:>40} ---- {} {} items",
-                "40 SLOTS (1 car)",
-                (item.stack_ratio * 40.0).round() as u32 * item.ingredient.stack_size,
-                item.ingredient.name
-            );
-        }
+    // Calculate ingredient ratios.
+    let item_ratios = calculate_ratios(ingredients, settings.exact)?;
 
-        if env_args.contains(&"2-car".into()) && env_args.contains(&"sc".into()) {
-            // "SC" refers to "same car" filtering. This requires each train car to follow the same filter.
-            println!(
-                "{:>40} ---- {} {} items",
-                "80 SLOTS (SC 2 cars)",
-                (item.stack_ratio * 40.0).round() as u32 * item.ingredient.stack_size * 2,
-                item.ingredient.name
-            );
-        }
+    #[cfg(debug_assertions)]
+    {
+        eprintln!("DEBUG main()[item_ratios]: {:?}", item_ratios);
+    }
 
-        if env_args.contains(&"2-car".into()) && env_args.contains(&"dc".into()) {
-            // "DC" refers to "different car" filtering. This allows each train car to follow their own unique filter.
-            println!(
-                "{:>40} ---- {} {} items",
-                "80 SLOTS (DC 2 cars)",
-                (item.stack_ratio * 80.0).round() as u32 * item.ingredient.stack_size,
-                item.ingredient.name
-            );
+    // Print machine-readable output or tables instead of the usual output if requested.
+    match settings.format {
+        report::Format::Text => {}
+        report::Format::Json => {
+            json::print_json(&item_ratios, settings);
+            return Ok(());
         }
-
-        if env_args.contains(&"3-car".into()) && env_args.contains(&"sc".into()) {
-            println!(
-                "{:>40} ---- {} {} items",
-                "120 SLOTS (SC 3 cars)",
-                (item.stack_ratio * 40.0).round() as u32 * item.ingredient.stack_size * 3,
-                item.ingredient.name
-            );
+        report::Format::Csv | report::Format::Markdown => {
+            table::print_table(&item_ratios, settings);
+            return Ok(());
         }
+    }
 
-        if env_args.contains(&"3-car".into()) && env_args.contains(&"dc".into()) {
-            println!(
-                "{:>40} ---- {} {} items",
-                "120 SLOTS (DC 3 cars)",
-                (item.stack_ratio * 120.0).round() as u32 * item.ingredient.stack_size,
-                item.ingredient.name
-            );
+    // Print blueprints of each train, its unloading station, its combinators and its dispatcher settings instead of the usual output if requested.
+    if settings.prints_blueprints() {
+        let trains = wagon::plan_trains(&item_ratios, selection, settings.pure);
+        if settings.blueprint {
+            blueprint::print_train_blueprints(
+                &item_ratios,
+                &trains,
+                settings.locomotives,
+                settings.schedule,
+                settings.interrupts,
+            )?;
         }
-
-        if env_args.contains(&"4-car".into()) && env_args.contains(&"sc".into()) {
-            println!(
-                "{:>40} ---- {} {} items",
-                "160 SLOTS (SC 4 cars)",
-                (item.stack_ratio * 40.0).round() as u32 * item.ingredient.stack_size * 4,
-                item.ingredient.name
-            );
+        if settings.station {
+            blueprint::print_station_blueprints(
+                &item_ratios,
+                &trains,
+                settings.locomotives,
+                selection.chest,
+                settings.sides,
+            )?;
         }
-
-        if env_args.contains(&"4-car".into()) && env_args.contains(&"dc".into()) {
-            println!(
-                "{:>40} ---- {} {} items",
-                "160 SLOTS (DC 4 cars)",
-                (item.stack_ratio * 160.0).round() as u32 * item.ingredient.stack_size,
-                item.ingredient.name
-            );
+        if settings.combinators {
+            blueprint::print_combinator_blueprints(&item_ratios, &trains, settings.locomotives);
         }
-
-        if env_args.contains(&"extra".into()) {
-            println!(
-                "{:>40} ---- {} {} items",
-                "48 SLOTS (1 steel chest)",
-                (item.stack_ratio * 48.0).round() as u32 * item.ingredient.stack_size,
-                item.ingredient.name
+        if let Some(dispatcher) = settings.dispatcher {
+            dispatch::print_dispatcher_settings(
+                dispatcher,
+                &item_ratios,
+                &trains,
+                settings.locomotives,
+                settings.train_limit,
             );
         }
+        return Ok(());
+    }
 
-        if env_args.contains(&"1-car".into()) {
-            println!(
-                "{:>40} ---- {} {} items",
-                "288 SLOTS (6 steel chests)",
-                (item.stack_ratio * 288.0).round() as u32 * item.ingredient.stack_size,
-                item.ingredient.name
-            );
-        }
+    // Print the comparison of train lengths instead of the usual output if requested.
+    if let Some(range) = settings.compare.clone() {
+        let same_car = selection.same_car && !selection.different_car;
+        let comparison =
+            compare::Comparison::new(&item_ratios, range, same_car, settings.target_error);
+        compare::print_comparison(&item_ratios, &comparison);
+        return Ok(());
+    }
 
-        if env_args.contains(&"2-car".into()) {
-            println!(
-                "{:>40} ---- {} {} items",
-                "624 SLOTS (13 steel chests)",
-                (item.stack_ratio * 624.0).round() as u32 * item.ingredient.stack_size,
-                item.ingredient.name
-            );
-        }
+    // Print calculated output.
+    let report = report::Report::new(&item_ratios, selection, settings.pure);
+    report.print(&item_ratios, settings.verbosity);
 
-        if env_args.contains(&"3-car".into()) {
-            println!(
-                "{:>40} ---- {} {} items",
-                "960 SLOTS (20 steel chests)",
-                (item.stack_ratio * 960.0).round() as u32 * item.ingredient.stack_size,
-                item.ingredient.name
-            );
-        }
+    // Print every step of the calculation.
+    if settings.explain {
+        explain::print_explanation(&item_ratios, &report);
+    }
 
-        if env_args.contains(&"4-car".into()) {
-            println!(
-                "{:>40} ---- {} {} items",
-                "1296 SLOTS (27 steel chests)",
-                (item.stack_ratio * 1296.0).round() as u32 * item.ingredient.stack_size,
-                item.ingredient.name
-            );
+    // Print the planned filters of each wagon.
+    if settings.filters {
+        for train in wagon::plan_trains(&item_ratios, selection, settings.pure) {
+            wagon::print_wagon_grids(&item_ratios, &train.wagons, train.title().as_str());
         }
+    }
 
-        println!("{:=^80}", " INSERTERS ");
+    // Print suggested station names.
+    if settings.names {
+        blueprint::print_station_names(&item_ratios, &selection.cars);
+    }
 
-        if env_args.contains(&"1-car".into()) {
-            println!(
-                "{:>40} ---- {} {} inserters",
-                "6 INSERTERS (6 steel chests)",
-                (item.ratio * 6.0).round() as u32,
-                item.ingredient.name
-            );
-        }
+    Ok(())
+}
 
-        if env_args.contains(&"2-car".into()) {
-            println!(
-                "{:>40} ---- {} {} inserters",
-                "13 INSERTERS (13 steel chests)",
-                (item.ratio * 13.0).round() as u32,
-                item.ingredient.name
-            );
+/// Audit a blueprint string against the plan's ingredients (if any), exiting if it cannot be audited.
+///
+/// # Parameters
+/// * `blueprint` - The blueprint string, or "-" to read it from STDIN.
+/// * `plan` - The plan whose ingredients the blueprint is meant to deliver.
+fn run_audit(blueprint: &str, plan: &plan::Plan) {
+    let blueprint = if blueprint == "-" {
+        match std::io::read_to_string(std::io::stdin()) {
+            Ok(blueprint) => blueprint,
+            Err(e) => {
+                println!("Could not read blueprint: {e}");
+                std::process::exit(EXIT_IO_ERROR);
+            }
         }
-
-        if env_args.contains(&"3-car".into()) {
-            println!(
-                "{:>40} ---- {} {} inserters",
-                "20 INSERTERS (20 steel chests)",
-                (item.ratio * 20.0).round() as u32,
-                item.ingredient.name
-            );
+    } else {
+        blueprint.to_string()
+    };
+
+    let target = match plan.ingredients() {
+        Ok(ingredients) => calculate_ratios(&ingredients, false).unwrap_or_default(),
+        Err(e) => {
+            println!("Invalid ingredient in plan: {e}");
+            std::process::exit(EXIT_INVALID_INPUT);
         }
+    };
 
-        if env_args.contains(&"4-car".into()) {
-            println!(
-                "{:>40} ---- {} {} inserters",
-                "27 INSERTERS (27 steel chests)",
-                (item.ratio * 27.0).round() as u32,
-                item.ingredient.name
-            );
-        }
+    if let Err(e) = audit::print_audit(blueprint.as_str(), &target) {
+        println!("{e}");
+        std::process::exit(EXIT_INVALID_INPUT);
     }
 }
 
-/// Prompt through STDIN for user string input with a specified prompt. If the user presses CTRL-C or otherwise passes SIGINT, this function will terminate the process on the spot.
+/// Calculate the ratio of each ingredient against the total of all ingredients.
 ///
 /// # Parameters
-/// * `rl_editor` - Rustyline [Editor](rustyline::Editor) used to read user's input.
-/// * `user_prompt` - User prompt given to the user to signal the need for input.
-fn prompt(
-    rl_editor: &mut rustyline::Editor<(), rustyline::history::FileHistory>,
-    user_prompt: &str,
-) -> String {
-    loop {
-        let readline = rl_editor.readline(user_prompt);
-        match readline {
-            Ok(line) => break line,
-            Err(e) => match e {
-                rustyline::error::ReadlineError::Interrupted => {
-                    // If interrupt signal is caught (with CTRL-C), exit here and now.
-
-                    #[cfg(debug_assertions)]
-                    {
-                        println!("Interrupt signal caught. Terminating.")
-                    }
+/// * `ingredients` - The ingredients to calculate the ratios of.
+/// * `exact` - Whether to also calculate the ratios as exact rational numbers, in which case slots are allocated from the exact ratios.
+///
+/// # Returns
+/// The ratio of each ingredient as [Ok]\(ratios), or [Err]\(message) if the amounts add up to nothing, or `exact` is set and the amounts are too large to calculate exactly.
+fn calculate_ratios(
+    ingredients: &[Ingredient],
+    exact: bool,
+) -> Result<Vec<IngredientRatio>, String> {
+    if let Some(item) = ingredients.iter().find(|item| item.stack_size == 0) {
+        return Err(format!("\"{}\" has a stack size of 0.", item.name));
+    }
 
-                    std::process::exit(0);
-                }
-                _ => {
-                    println!("Input error: {}", e);
-                    continue;
-                }
-            },
+    // Total number of items specified in user-given amounts.
+    let mut total_items = 0.0;
+    for item in ingredients {
+        total_items += item.amount.to_f64();
+    }
+
+    // Total item stacks specified in user-given amounts.
+    let mut total_stacks = 0.0;
+    for item in ingredients {
+        total_stacks += item.amount.to_f64() / item.stack_size as f64;
+    }
+
+    // Every ratio is divided by the totals, which must be positive for the ratios to mean anything.
+    let positive = |total: f64| total.is_finite() && total > 0.0;
+    if !positive(total_items) || !positive(total_stacks) {
+        return Err("The amounts given add up to nothing, so they have no ratio. Give every ingredient an amount of more than 0.".into());
+    }
+
+    // Exact totals of items and item stacks, if calculating exactly.
+    let too_large = || {
+        "The amounts given are too large to calculate exactly. Try again without the exact option."
+            .to_string()
+    };
+    let exact_totals = if exact {
+        let mut total_items = Rational::ZERO;
+        let mut total_stacks = Rational::ZERO;
+        for item in ingredients {
+            total_items = total_items.checked_add(item.amount).ok_or_else(too_large)?;
+            total_stacks = item
+                .exact_stacks()
+                .and_then(|stacks| total_stacks.checked_add(stacks))
+                .ok_or_else(too_large)?;
         }
+        Some((total_items, total_stacks))
+    } else {
+        None
+    };
+
+    let mut item_ratios: Vec<IngredientRatio> = Vec::new();
+    for item in ingredients {
+        let exact = match exact_totals {
+            Some((total_items, total_stacks)) => Some(ExactRatio {
+                ratio: item.amount.checked_div(total_items).ok_or_else(too_large)?,
+                stack_ratio: item
+                    .exact_stacks()
+                    .and_then(|stacks| stacks.checked_div(total_stacks))
+                    .ok_or_else(too_large)?,
+            }),
+            None => None,
+        };
+
+        item_ratios.push(IngredientRatio {
+            ingredient: item.clone(),
+            ratio: item.amount.to_f64() / total_items,
+            stack_ratio: (item.amount.to_f64() / item.stack_size as f64) / total_stacks,
+            exact,
+        });
     }
+
+    Ok(item_ratios)
 }
 
-/// Prompt through STDIN for user string input with a specified prompt. Will return [Some]\(input) if the user provides input, or [None] if the user elects to press CTRL-C or otherwise pass SIGINT. This function will _NOT_ kill the running process if the user presses CTRl-C or passes SIGINT.
+/// Prompt through STDIN for user string input with a specified prompt. Will return [Some]\([Some]\(input)) if the user provides input, [Some]\([None]) if the user presses CTRL-D to signal they are done, or [None] if the user elects to press CTRL-C or otherwise pass SIGINT. This function will _NOT_ kill the running process if the user presses CTRl-C or passes SIGINT, but will if input cannot be read. Every line of input is added to the input history, which is saved straight away so that it is kept however the process ends.
 ///
 /// # Parameters
 /// * `rl_editor` - Rustyline [Editor](rustyline::Editor) used to read user's input.
 /// * `user_prompt` - User prompt given to the user to signal the need for input.
-fn prompt_or_sigint(
-    rl_editor: &mut rustyline::Editor<(), rustyline::history::FileHistory>,
-    user_prompt: &str,
-) -> Option<String> {
-    loop {
-        let readline = rl_editor.readline(user_prompt);
-        match readline {
-            Ok(line) => break Some(line),
-            Err(e) => match e {
-                rustyline::error::ReadlineError::Interrupted => {
-                    // If interrupt signal is caught (with CTRL-C), return as None.
-                    return None;
-                }
-                _ => {
-                    println!("Input error: {}", e);
-                    continue;
-                }
-            },
+fn prompt_or_sigint(rl_editor: &mut LineEditor, user_prompt: &str) -> Option<Option<String>> {
+    match rl_editor.readline(user_prompt) {
+        Ok(line) => {
+            if !line.trim().is_empty() {
+                let _ = rl_editor.add_history_entry(line.as_str());
+                session::save_history(rl_editor);
+            }
+            Some(Some(line))
+        }
+        // If interrupt signal is caught (with CTRL-C), return as None.
+        Err(rustyline::error::ReadlineError::Interrupted) => None,
+        // If end of input is reached (with CTRL-D), the user is done.
+        Err(rustyline::error::ReadlineError::Eof) => Some(None),
+        Err(e) => {
+            println!("Input error: {}", e);
+            std::process::exit(EXIT_IO_ERROR);
         }
     }
 }
 
-/// Prompt and parse through STDIN for user generic input with a specified prompt. This function will repeat the prompt until a valid type is provided.
+/// Prompt and parse through STDIN for user generic input with a specified prompt. This function will repeat the prompt until a valid type is provided, or return [None] if the user presses CTRL-D to signal they are done or CTRL-C to cancel.
 ///
 /// # Generic parameters
 /// * `T` - The type to parse the user input into. This must implement [FromStr].
@@ -404,14 +427,14 @@ fn prompt_or_sigint(
 /// * `rl_editor` - Rustyline [Editor](rustyline::Editor) used to read user's input.
 /// * `user_prompt` - User prompt given to the user to signal the need for input.
 fn prompt_and_parse<T: FromStr<Err = impl Display>>(
-    rl_editor: &mut rustyline::Editor<(), rustyline::history::FileHistory>,
+    rl_editor: &mut LineEditor,
     user_prompt: &str,
-) -> T {
+) -> Option<T> {
     loop {
-        let input_string = prompt(rl_editor, user_prompt);
+        let input_string = prompt_or_sigint(rl_editor, user_prompt)??;
         match input_string.parse::<T>() {
             Ok(t) => {
-                break t;
+                break Some(t);
             }
             Err(e) => {
                 println!("Error parsing input: {}", e);
@@ -421,55 +444,378 @@ fn prompt_and_parse<T: FromStr<Err = impl Display>>(
     }
 }
 
-/// Prompt the user for the ingredients they wish to calculate over.
+/// Prompt for the amount of an ingredient, accepting arithmetic, thousands suffixes, stacks and rates (see [amount::AmountInput]), and print the amount back in items (or items per second) so the user can check it. This function will repeat the prompt until a valid amount is provided, or return [None] if the user presses CTRL-D to signal they are done or CTRL-C to cancel.
 ///
 /// # Parameters
 /// * `rl_editor` - Rustyline [Editor](rustyline::Editor) used to read user's input.
-fn prompt_ingredients(
-    rl_editor: &mut rustyline::Editor<(), rustyline::history::FileHistory>,
-) -> Vec<Ingredient> {
-    // Prompt for all ingredients.
-    let mut ingredients: Vec<Ingredient> = Vec::new();
+/// * `user_prompt` - User prompt given to the user to signal the need for input.
+/// * `stack_size` - The stack size of the ingredient, to convert stacks to items with.
+/// * `rate` - Whether the amount must be a rate (or must not be one), if the ingredients entered so far have settled it.
+fn prompt_amount(
+    rl_editor: &mut LineEditor,
+    user_prompt: &str,
+    stack_size: u32,
+    rate: Option<bool>,
+) -> Option<amount::Amount> {
     loop {
-        let ingredient_name =
-            match prompt_or_sigint(rl_editor, format!("Item name (CTRL-C if done) > ").as_str()) {
-                Some(t) => t,
-                None => break,
+        let input = prompt_and_parse::<amount::AmountInput>(rl_editor, user_prompt)?;
+        let Some(amount) = input.to_amount(stack_size) else {
+            println!("Error parsing input: amount is too large");
+            continue;
+        };
+
+        match rate {
+            _ if amount.value <= Rational::ZERO => {
+                println!("The amount must be more than 0.")
+            }
+            Some(true) if !amount.rate => {
+                println!(
+                    "The other ingredients are rates. Give this one as a rate too (e.g. \"15/s\")."
+                )
+            }
+            Some(false) if amount.rate => {
+                println!("The other ingredients are amounts. Give this one as an amount too (e.g. \"1200\").")
             }
-            .to_lowercase();
+            _ => {
+                println!("= {amount}");
+                break Some(amount);
+            }
+        }
+    }
+}
+
+/// Prompt for the stack size of an item, repeating the prompt until a stack size of 1 or more is provided, or returning [None] if the user presses CTRL-D to signal they are done or CTRL-C to cancel.
+///
+/// # Parameters
+/// * `rl_editor` - Rustyline [Editor](rustyline::Editor) used to read user's input.
+/// * `user_prompt` - User prompt given to the user to signal the need for input.
+fn prompt_stack_size(rl_editor: &mut LineEditor, user_prompt: &str) -> Option<u32> {
+    loop {
+        match prompt_and_parse::<u32>(rl_editor, user_prompt)? {
+            0 => println!("The stack size must be 1 or more."),
+            stack_size if stack_size > MAX_STACK_SIZE => {
+                println!("The stack size must be {MAX_STACK_SIZE} or less.")
+            }
+            stack_size => break Some(stack_size),
+        }
+    }
+}
+
+/// Prompt the user for the ingredients they wish to calculate over, adding each one to `ingredients` as it is entered (with its amounts added together with an ingredient already given for the same item). The user is done when they press CTRL-C or CTRL-D at the item name prompt. Pressing either at the stack size or amount prompt discards only the ingredient being entered and prompts for the next item name, so the process is never ended from here.
+///
+/// # Parameters
+/// * `rl_editor` - Rustyline [Editor](rustyline::Editor) used to read user's input.
+/// * `ingredients` - The ingredients given so far, to add to.
+fn prompt_ingredients(rl_editor: &mut LineEditor, ingredients: &mut Vec<Ingredient>) {
+    // Prompt for all ingredients, as rates if the ingredients given so far are rates.
+    let mut rates: Option<bool> = ingredients.first().map(|item| item.rate);
+    loop {
+        // Complete and hint item names only while an item name is being typed.
+        set_item_helper_active(rl_editor, true);
+        let ingredient_name = prompt_or_sigint(rl_editor, "Item name (CTRL-C if done) > ");
+        set_item_helper_active(rl_editor, false);
+
+        let ingredient_name = match ingredient_name {
+            Some(Some(t)) => data::canonical_item_name(t.as_str()),
+            Some(None) | None => break,
+        };
+        if ingredient_name.is_empty() {
+            continue;
+        }
 
-        let ingredient_amount = prompt_and_parse::<f64>(
+        // An item given again keeps the stack size it was first given with.
+        let earlier = ingredients
+            .iter()
+            .find(|item| item.name == ingredient_name)
+            .map(|item| item.stack_size);
+        let ingredient_stack_size =
+            match earlier.or(data::fetch_item_stack_size(ingredient_name.as_str())) {
+                Some(stack_size) => stack_size,
+                None => match prompt_stack_size(
+                    rl_editor,
+                    format!("{} stack size > ", ingredient_name.to_uppercase()).as_str(),
+                ) {
+                    Some(stack_size) => stack_size,
+                    None => {
+                        println!("Discarded {}.", ingredient_name.to_uppercase());
+                        continue;
+                    }
+                },
+            };
+
+        // Ratios are only meaningful if every ingredient is an amount, or every ingredient is a rate.
+        let Some(ingredient_amount) = prompt_amount(
             rl_editor,
             format!("{} amount > ", ingredient_name.to_uppercase()).as_str(),
+            ingredient_stack_size,
+            rates,
+        ) else {
+            println!("Discarded {}.", ingredient_name.to_uppercase());
+            continue;
+        };
+
+        let ingredient = Ingredient::from_amount(
+            ingredient_name.as_str(),
+            ingredient_amount,
+            ingredient_stack_size,
         );
+        match ingredient.and_then(|ingredient| merge_ingredient(ingredients, ingredient)) {
+            Ok(merged) => {
+                rates = Some(ingredient_amount.rate);
+                if merged {
+                    println!(
+                        "{} was already given, so its amounts were added together.",
+                        ingredient_name.to_uppercase()
+                    );
+                }
+            }
+            Err(e) => println!("Invalid ingredient: {e}"),
+        }
+    }
+}
+
+/// Set whether the editor's [helper::ItemHelper] completes and hints item names.
+fn set_item_helper_active(rl_editor: &mut LineEditor, active: bool) {
+    if let Some(helper) = rl_editor.helper_mut() {
+        helper.active = active;
+    }
+}
+
+/// Read the ingredients to calculate over from piped input, one ingredient per line in the form "NAME,AMOUNT" or "NAME,AMOUNT,STACK". Blank lines and lines starting with "#" are skipped. If a line is invalid or input cannot be read, this function will terminate the process.
+///
+/// # Parameters
+/// * `reader` - Reader to read the lines of input from.
+fn read_ingredients(reader: impl BufRead) -> Vec<Ingredient> {
+    let mut ingredients: Vec<Ingredient> = Vec::new();
+
+    for (number, line) in reader.lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                println!("Input error: {}", e);
+                std::process::exit(EXIT_IO_ERROR);
+            }
+        };
 
-        let ingredient_stack_size = match data::fetch_item_stack_size(ingredient_name.as_str()) {
-            Some(stack_size) => stack_size,
-            None => prompt_and_parse::<u32>(
-                rl_editor,
-                format!("{} stack size > ", ingredient_name.to_uppercase()).as_str(),
-            ),
+        let Some(ingredient) = parse_ingredient_line(line.as_str()).transpose() else {
+            continue;
         };
 
-        ingredients.push(Ingredient {
-            name: ingredient_name,
-            amount: ingredient_amount,
-            stack_size: ingredient_stack_size,
-        });
+        match ingredient.and_then(|ingredient| merge_ingredient(&mut ingredients, ingredient)) {
+            Ok(_) => {}
+            Err(e) => {
+                println!("Invalid ingredient on line {}: {}", number + 1, e);
+                std::process::exit(EXIT_INVALID_INPUT);
+            }
+        }
     }
 
     ingredients
 }
 
+/// Parse a line of piped input in the form "NAME,AMOUNT" or "NAME,AMOUNT,STACK".
+///
+/// # Returns
+/// The ingredient as [Ok]\([Some]\(ingredient)), [Ok]\([None]) if the line is blank or a "#" comment, or [Err]\(message) describing why the line is invalid.
+fn parse_ingredient_line(line: &str) -> Result<Option<Ingredient>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let mut fields = line.splitn(3, ',');
+    match (fields.next(), fields.next()) {
+        (Some(name), Some(amount)) => parse_ingredient(name, amount, fields.next()).map(Some),
+        _ => Err("expected NAME,AMOUNT or NAME,AMOUNT,STACK".into()),
+    }
+}
+
+/// Parse an ingredient passed as an argument in the form "NAME=AMOUNT" or "NAME=AMOUNT:STACK", where STACK overrides the stack size of the item.
+///
+/// # Returns
+/// The ingredient as [Ok]\(ingredient), or [Err]\(message) describing why the argument is invalid.
+fn parse_ingredient_arg(arg: &str) -> Result<Ingredient, String> {
+    let (name, value) = arg
+        .split_once('=')
+        .ok_or("expected NAME=AMOUNT or NAME=AMOUNT:STACK")?;
+
+    match value.split_once(':') {
+        Some((amount, stack_size)) => parse_ingredient(name, amount, Some(stack_size)),
+        None => parse_ingredient(name, value, None),
+    }
+}
+
+/// Parse an ingredient from its name, amount and optional stack size override. If no stack size is given, the item's known stack size is used.
+///
+/// # Returns
+/// The ingredient as [Ok]\(ingredient), or [Err]\(message) describing why it is invalid.
+fn parse_ingredient(
+    name: &str,
+    amount: &str,
+    stack_size: Option<&str>,
+) -> Result<Ingredient, String> {
+    let name = data::canonical_item_name(name);
+    if name.is_empty() {
+        return Err("missing item name".into());
+    }
+
+    let amount = amount
+        .trim()
+        .parse::<Rational>()
+        .map_err(|e| format!("invalid amount: {e}"))?;
+
+    let stack_size = match stack_size {
+        Some(stack_size) => stack_size
+            .trim()
+            .parse::<u32>()
+            .map_err(|e| format!("invalid stack size: {e}"))?,
+        None => data::fetch_item_stack_size(name.as_str()).ok_or(format!(
+            "unknown stack size for \"{name}\", give it after the amount"
+        ))?,
+    };
+
+    Ingredient::new(name.as_str(), amount, stack_size)
+}
+
+/// Add an ingredient to a list of ingredients, adding its amount to the ingredient of the same name if there is one already, so that each item is only calculated over once.
+///
+/// # Returns
+/// [Ok]\(true) if the amount was added to an earlier ingredient, [Ok]\(false) if the ingredient was added as a new one, or [Err]\(message) if it is a rate where the other ingredients are amounts (or the other way round), the earlier ingredient has a different stack size, or the amounts are too large to add.
+fn merge_ingredient(
+    ingredients: &mut Vec<Ingredient>,
+    ingredient: Ingredient,
+) -> Result<bool, String> {
+    if let Some(other) = ingredients.iter().find(|item| item.rate != ingredient.rate) {
+        return Err(format!(
+            "\"{}\" is given as {}, but \"{}\" is given as {}",
+            ingredient.name,
+            ingredient.kind(),
+            other.name,
+            other.kind()
+        ));
+    }
+
+    let Some(earlier) = ingredients
+        .iter_mut()
+        .find(|item| item.name == ingredient.name)
+    else {
+        ingredients.push(ingredient);
+        return Ok(false);
+    };
+
+    if earlier.stack_size != ingredient.stack_size {
+        return Err(format!(
+            "\"{}\" is given twice with different stack sizes ({} and {})",
+            ingredient.name, earlier.stack_size, ingredient.stack_size
+        ));
+    }
+    earlier.amount = earlier
+        .amount
+        .checked_add(ingredient.amount)
+        .ok_or(format!(
+            "the amounts of \"{}\" are too large to add",
+            ingredient.name
+        ))?;
+    Ok(true)
+}
+
+/// Check that a new amount for the ingredient at `index` agrees with the other ingredients, which must all be rates if the amount is a rate, or all amounts if it is not.
+///
+/// # Returns
+/// [Ok] if it agrees, or [Err]\(message) asking for the amount to be given the other way.
+fn check_rate(ingredients: &[Ingredient], index: usize, rate: bool) -> Result<(), String> {
+    let other = ingredients
+        .iter()
+        .enumerate()
+        .find(|(other, item)| *other != index && item.rate != rate);
+    match other {
+        Some((_, item)) if item.rate => Err(
+            "The other ingredients are rates. Give this one as a rate too (e.g. \"15/s\").".into(),
+        ),
+        Some(_) => Err(
+            "The other ingredients are amounts. Give this one as an amount too (e.g. \"1200\")."
+                .into(),
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Merge ingredients of the same name into one, adding their amounts (see [merge_ingredient]). Ingredients are kept in the order they were first given.
+///
+/// # Returns
+/// The merged ingredients as [Ok]\(ingredients), or [Err]\(message) if two ingredients of the same name cannot be merged.
+fn merge_ingredients(ingredients: Vec<Ingredient>) -> Result<Vec<Ingredient>, String> {
+    let mut merged: Vec<Ingredient> = Vec::new();
+    for ingredient in ingredients {
+        merge_ingredient(&mut merged, ingredient)?;
+    }
+    Ok(merged)
+}
+
 /// Ingredient as defined by the user.
 #[derive(Debug, Clone)]
 struct Ingredient {
     /// The name of the ingredient.
     name: String,
-    /// The amount of the ingredient needed.
-    amount: f64,
+    /// The amount of the ingredient needed. This is kept exact, as it may be given as a fraction.
+    amount: Rational,
     /// The stack size of the ingredient.
     stack_size: u32,
+    /// Whether the amount is a rate (in items per second) rather than a number of items. Ratios are only meaningful if every ingredient agrees.
+    rate: bool,
+}
+
+impl Ingredient {
+    /// Create an ingredient, checking that its amount and stack size can be calculated over. The name is made canonical with [data::canonical_item_name].
+    ///
+    /// # Returns
+    /// The ingredient as [Ok]\(ingredient), or [Err]\(message) if the amount is not more than 0 or the stack size is not from 1 to [MAX_STACK_SIZE].
+    fn new(name: &str, amount: Rational, stack_size: u32) -> Result<Ingredient, String> {
+        let name = data::canonical_item_name(name);
+        if amount <= Rational::ZERO {
+            return Err(format!(
+                "the amount of \"{name}\" must be more than 0, not {amount}"
+            ));
+        }
+        if stack_size == 0 || stack_size > MAX_STACK_SIZE {
+            return Err(format!(
+                "the stack size of \"{name}\" must be from 1 to {MAX_STACK_SIZE}, not {stack_size}"
+            ));
+        }
+
+        Ok(Ingredient {
+            name,
+            amount,
+            stack_size,
+            rate: false,
+        })
+    }
+
+    /// Create an ingredient from an amount that may be a rate (see [Ingredient::new]).
+    fn from_amount(
+        name: &str,
+        amount: amount::Amount,
+        stack_size: u32,
+    ) -> Result<Ingredient, String> {
+        Ok(Ingredient {
+            rate: amount.rate,
+            ..Ingredient::new(name, amount.value, stack_size)?
+        })
+    }
+
+    /// What the amount of the ingredient is given as, for messages ("a rate" or "an amount").
+    fn kind(&self) -> &'static str {
+        match self.rate {
+            true => "a rate",
+            false => "an amount",
+        }
+    }
+
+    /// The exact number of stacks the needed amount of the ingredient takes up, or [None] if it is too large to calculate exactly.
+    fn exact_stacks(&self) -> Option<Rational> {
+        self.amount
+            .checked_div(Rational::from_integer(self.stack_size as i128))
+    }
 }
 
 /// The ratio of the needed ingredient as an f64 between 0 and 1.
@@ -481,4 +827,181 @@ struct IngredientRatio {
     ratio: f64,
     /// The ratio of the ingredient calculated to be needed. This takes stack size into account. This value is between 0 and 1.
     stack_ratio: f64,
+    /// The ratios of the ingredient as exact rational numbers, if calculating exactly.
+    exact: Option<ExactRatio>,
+}
+
+/// The ratios of a needed ingredient as exact rational numbers between 0 and 1.
+#[derive(Debug, Clone)]
+struct ExactRatio {
+    /// The exact ratio of the ingredient. This does _NOT_ take stack size into account.
+    ratio: Rational,
+    /// The exact ratio of the ingredient. This takes stack size into account.
+    stack_ratio: Rational,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An ingredient of `value` items, or items per second if `rate` is set.
+    fn ingredient(name: &str, value: i128, rate: bool) -> Ingredient {
+        let amount = amount::Amount {
+            value: Rational::from_integer(value),
+            rate,
+        };
+        Ingredient::from_amount(name, amount, 100).unwrap()
+    }
+
+    #[test]
+    fn parses_ingredient_arguments() {
+        let ingredient = parse_ingredient_arg("Iron-Plate=1200").unwrap();
+        assert_eq!(ingredient.name, "iron plate");
+        assert_eq!(ingredient.amount, Rational::from_integer(1200));
+        assert_eq!(ingredient.stack_size, 100);
+
+        let ingredient = parse_ingredient_arg("steel=300:50").unwrap();
+        assert_eq!(ingredient.amount, Rational::from_integer(300));
+        assert_eq!(ingredient.stack_size, 50);
+
+        let ingredient = parse_ingredient_arg("widget=1/3:7").unwrap();
+        assert_eq!(ingredient.amount.to_string(), "1/3");
+    }
+
+    #[test]
+    fn reads_piped_ingredients() {
+        let input = "# station\niron plate,1200\n\n  steel , 300 , 50 \nIron-Plate,300\n";
+        let ingredients = read_ingredients(input.as_bytes());
+        assert_eq!(ingredients.len(), 2);
+        assert_eq!(ingredients[0].name, "iron plate");
+        assert_eq!(ingredients[0].amount, Rational::from_integer(1500));
+        assert_eq!(ingredients[1].name, "steel plate");
+        assert_eq!(ingredients[1].stack_size, 50);
+        assert!(read_ingredients("".as_bytes()).is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_piped_lines() {
+        assert!(matches!(parse_ingredient_line("  # comment"), Ok(None)));
+        for line in [
+            "iron plate",
+            "iron plate 1200",
+            ",1200",
+            "iron plate,x",
+            "iron plate,0",
+            "iron plate,1200,0",
+            "iron plate,1200,50,extra",
+        ] {
+            assert!(parse_ingredient_line(line).is_err(), "{line:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_ingredient_arguments() {
+        for arg in [
+            "iron plate",
+            "=1200",
+            "iron plate=",
+            "iron plate=x",
+            "iron plate=0",
+            "iron plate=-5",
+            "iron plate=1200:0",
+            "iron plate=1200:x",
+            "unknown widget=1200",
+        ] {
+            assert!(parse_ingredient_arg(arg).is_err(), "{arg:?}");
+        }
+    }
+
+    #[test]
+    fn exact_ratios_too_large_to_calculate_are_errors() {
+        let huge = Rational::from_integer(i128::MAX / 3);
+        let ingredients = vec![
+            Ingredient::new("iron plate", huge, 7).unwrap(),
+            Ingredient::new("copper plate", huge, 11).unwrap(),
+        ];
+        assert!(calculate_ratios(&ingredients, false).is_ok());
+        let error = calculate_ratios(&ingredients, true).unwrap_err();
+        assert!(error.contains("too large"), "{error}");
+    }
+
+    #[test]
+    fn merging_keeps_rates_and_amounts_apart() {
+        let mut ingredients = vec![ingredient("iron plate", 15, true)];
+        assert_eq!(
+            merge_ingredient(&mut ingredients, ingredient("steel plate", 5, true)),
+            Ok(false)
+        );
+
+        let error = merge_ingredient(&mut ingredients, ingredient("copper plate", 1200, false))
+            .unwrap_err();
+        assert!(error.contains("given as an amount"), "{error}");
+        let error =
+            merge_ingredient(&mut ingredients, ingredient("iron plate", 1200, false)).unwrap_err();
+        assert!(error.contains("given as an amount"), "{error}");
+        assert_eq!(ingredients.len(), 2);
+    }
+
+    #[test]
+    fn checks_new_amounts_against_the_other_ingredients() {
+        let ingredients = vec![
+            ingredient("iron plate", 15, true),
+            ingredient("steel plate", 5, true),
+        ];
+        assert!(check_rate(&ingredients, 0, true).is_ok());
+        assert!(check_rate(&ingredients, 0, false).is_err());
+        assert!(check_rate(&ingredients[..1], 0, false).is_ok());
+    }
+
+    #[test]
+    fn merges_an_item_given_in_any_spelling() {
+        let ingredients = merge_ingredients(vec![
+            Ingredient::new("iron plate", Rational::from_integer(1200), 100).unwrap(),
+            Ingredient::new("steel", Rational::from_integer(300), 100).unwrap(),
+            Ingredient::new(" Iron-Plate ", Rational::new(1, 2).unwrap(), 100).unwrap(),
+        ])
+        .unwrap();
+        assert_eq!(ingredients.len(), 2);
+        assert_eq!(ingredients[0].name, "iron plate");
+        assert_eq!(ingredients[0].amount, Rational::new(2401, 2).unwrap());
+        assert_eq!(ingredients[1].name, "steel plate");
+    }
+
+    #[test]
+    fn merging_rejects_conflicting_duplicates() {
+        let mut ingredients =
+            vec![Ingredient::new("iron plate", Rational::from_integer(1200), 100).unwrap()];
+        let error = merge_ingredient(
+            &mut ingredients,
+            Ingredient::new("iron plate", Rational::from_integer(300), 50).unwrap(),
+        )
+        .unwrap_err();
+        assert!(error.contains("different stack sizes"), "{error}");
+
+        let huge = Rational::from_integer(i128::MAX);
+        let mut ingredients = vec![Ingredient::new("iron plate", huge, 100).unwrap()];
+        let error = merge_ingredient(
+            &mut ingredients,
+            Ingredient::new("iron plate", huge, 100).unwrap(),
+        )
+        .unwrap_err();
+        assert!(error.contains("too large"), "{error}");
+        assert_eq!(ingredients[0].amount, huge);
+    }
+
+    #[test]
+    fn rejects_invalid_amounts_and_stack_sizes() {
+        assert!(Ingredient::new("iron plate", Rational::ZERO, 100).is_err());
+        assert!(Ingredient::new("iron plate", Rational::from_integer(-5), 100).is_err());
+        assert!(Ingredient::new("iron plate", Rational::from_integer(1200), 0).is_err());
+        assert!(Ingredient::new(
+            "iron plate",
+            Rational::from_integer(1200),
+            MAX_STACK_SIZE + 1
+        )
+        .is_err());
+        assert!(
+            Ingredient::new("iron plate", Rational::from_integer(1200), MAX_STACK_SIZE).is_ok()
+        );
+    }
 }
@@ -0,0 +1,406 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    amount::Amount,
+    args::Settings,
+    data,
+    dispatch::Dispatcher,
+    merge_ingredients,
+    rational::Rational,
+    report::{Chest, Format, Verbosity},
+    schedule::LoadWait,
+    Ingredient,
+};
+
+/// A station plan, describing the ingredients to calculate over and how to calculate over them. Plans are read from and written to TOML files, or JSON files if their extension is ".json".
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Plan {
+    /// The type of chest the train is unloaded into.
+    #[serde(default)]
+    pub chest: Chest,
+    /// The trains to calculate over.
+    #[serde(default)]
+    pub train: TrainPlan,
+    /// Which extra output to print.
+    #[serde(default)]
+    pub output: OutputPlan,
+    /// The ingredients to calculate over.
+    pub ingredients: Vec<PlanIngredient>,
+}
+
+/// The trains a [Plan] calculates over.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrainPlan {
+    /// The cargo wagon counts of the trains.
+    pub cars: Vec<u32>,
+    /// Whether to calculate "SC" (same-car) filtering.
+    pub same_car: bool,
+    /// Whether to calculate "DC" (different-car) filtering.
+    pub different_car: bool,
+    /// Whether to dedicate whole wagons to a single ingredient where it fills them.
+    pub pure: bool,
+    /// The number of locomotives at the front of each train in blueprints.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locomotives: Option<u32>,
+    /// The number of sides of the rails unloading stations unload from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sides: Option<u32>,
+    /// The number of trains allowed at each station at once, for train dispatcher mods.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub train_limit: Option<u32>,
+}
+
+/// Which extra output a [Plan] prints.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputPlan {
+    /// Whether to print some extras.
+    pub extra: bool,
+    /// Whether to print the filter of every slot of every wagon.
+    pub filters: bool,
+    /// Whether to print suggested station names.
+    pub names: bool,
+    /// Whether to print every step of the calculation.
+    pub explain: bool,
+    /// Whether to print a blueprint string of each train instead of the usual output.
+    pub blueprint: bool,
+    /// How long each train waits at its loading station, if train blueprints are to be given a schedule.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<LoadWait>,
+    /// Whether to write train schedules as Factorio 2.0 interrupts.
+    pub interrupts: bool,
+    /// Whether to print a blueprint string of an unloading station for each train length instead of the usual output.
+    pub station: bool,
+    /// Whether to print a blueprint string of constant combinators for each train instead of the usual output.
+    pub combinators: bool,
+    /// The train dispatcher mod to print station settings for, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dispatcher: Option<Dispatcher>,
+    /// Whether to calculate with exact fractions.
+    pub exact: bool,
+    /// How much of the output to print.
+    pub verbosity: Verbosity,
+    /// The format to print the output in.
+    pub format: Format,
+}
+
+/// An ingredient as written in a [Plan]. Either its amount or its rate must be given, and every ingredient in a plan must use the same one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanIngredient {
+    /// The name of the ingredient.
+    pub name: String,
+    /// The amount of the ingredient needed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<Rational>,
+    /// The rate the ingredient is needed at (e.g. in items per second).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate: Option<Rational>,
+    /// The stack size of the ingredient, overriding the stack size it is known to have.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stack_size: Option<u32>,
+}
+
+impl Plan {
+    /// Build a plan of the ingredients and settings calculated over, to be read back with `--plan`. Options that only apply to other options are left out unless those options are set.
+    pub fn from_settings(settings: &Settings, ingredients: &[Ingredient]) -> Plan {
+        let selection = &settings.selection;
+
+        Plan {
+            chest: selection.chest,
+            train: TrainPlan {
+                cars: selection.cars.clone(),
+                same_car: selection.same_car,
+                different_car: selection.different_car,
+                pure: settings.pure,
+                locomotives: Some(settings.locomotives).filter(|_| settings.prints_blueprints()),
+                sides: Some(settings.sides).filter(|_| settings.station),
+                train_limit: Some(settings.train_limit).filter(|_| settings.dispatcher.is_some()),
+            },
+            output: OutputPlan {
+                extra: selection.extra,
+                filters: settings.filters,
+                names: settings.names,
+                explain: settings.explain,
+                blueprint: settings.blueprint,
+                schedule: settings.schedule,
+                interrupts: settings.interrupts,
+                station: settings.station,
+                combinators: settings.combinators,
+                dispatcher: settings.dispatcher,
+                exact: settings.exact,
+                verbosity: settings.verbosity,
+                format: settings.format,
+            },
+            ingredients: Plan::plan_ingredients(ingredients),
+        }
+    }
+
+    /// Read a plan from a TOML or JSON file.
+    ///
+    /// # Returns
+    /// The plan as [Ok]\(plan), or [Err]\(message) if the file cannot be read or is not a valid plan.
+    pub fn load(path: &Path) -> Result<Plan, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read plan \"{}\": {}", path.display(), e))?;
+
+        let plan = if is_json(path) {
+            serde_json::from_str(contents.as_str()).map_err(|e| e.to_string())
+        } else {
+            toml::from_str(contents.as_str()).map_err(|e| e.to_string())
+        };
+
+        plan.map_err(|e| format!("Invalid plan \"{}\": {}", path.display(), e))
+    }
+
+    /// Write the plan to a TOML or JSON file, replacing the file if it exists.
+    ///
+    /// # Returns
+    /// [Ok] if the plan was written, or [Err]\(message) if it could not be.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let contents = if is_json(path) {
+            serde_json::to_string_pretty(self)
+                .map(|json| json + "\n")
+                .map_err(|e| e.to_string())
+        } else {
+            toml::to_string_pretty(self).map_err(|e| e.to_string())
+        }
+        .map_err(|e| format!("Could not write plan \"{}\": {}", path.display(), e))?;
+
+        std::fs::write(path, contents)
+            .map_err(|e| format!("Could not write plan \"{}\": {}", path.display(), e))
+    }
+
+    /// The ingredients of the plan, with stack sizes filled in from known items where not overridden, and ingredients of the same item merged into one.
+    ///
+    /// # Returns
+    /// The ingredients as [Ok]\(ingredients), or [Err]\(message) if an ingredient is missing its amount or stack size, has an amount or stack size of 0, or the plan mixes amounts and rates.
+    pub fn ingredients(&self) -> Result<Vec<Ingredient>, String> {
+        let uses_rates = self.ingredients.iter().any(|item| item.rate.is_some());
+
+        self.ingredients
+            .iter()
+            .map(|item| {
+                let name = data::canonical_item_name(item.name.as_str());
+
+                // Ratios are the same whether they are calculated from amounts or rates, so long as every ingredient uses the same one.
+                let amount = match (item.amount, item.rate) {
+                    (Some(amount), None) if !uses_rates => Amount {
+                        value: amount,
+                        rate: false,
+                    },
+                    (None, Some(rate)) => Amount {
+                        value: rate,
+                        rate: true,
+                    },
+                    (Some(_), Some(_)) => {
+                        return Err(format!("\"{name}\" has both an amount and a rate"))
+                    }
+                    (None, None) => return Err(format!("\"{name}\" has no amount or rate")),
+                    (Some(_), None) => {
+                        return Err(format!(
+                            "\"{name}\" has an amount, but other ingredients have rates"
+                        ))
+                    }
+                };
+
+                let stack_size = match item.stack_size {
+                    Some(stack_size) => stack_size,
+                    None => data::fetch_item_stack_size(name.as_str())
+                        .ok_or(format!("\"{name}\" has no known stack size, give it one"))?,
+                };
+
+                Ingredient::from_amount(name.as_str(), amount, stack_size)
+            })
+            .collect::<Result<Vec<Ingredient>, String>>()
+            .and_then(merge_ingredients)
+    }
+
+    /// Describe the given ingredients as plan ingredients, with the amount of each written as a rate if it was given as one. Stack sizes are only written where they differ from the item's known stack size.
+    pub fn plan_ingredients(ingredients: &[Ingredient]) -> Vec<PlanIngredient> {
+        ingredients
+            .iter()
+            .map(|item| PlanIngredient {
+                name: item.name.clone(),
+                amount: Some(item.amount).filter(|_| !item.rate),
+                rate: Some(item.amount).filter(|_| item.rate),
+                stack_size: match data::fetch_item_stack_size(item.name.as_str()) {
+                    Some(stack_size) if stack_size == item.stack_size => None,
+                    _ => Some(item.stack_size),
+                },
+            })
+            .collect()
+    }
+}
+
+/// Whether the file at `path` is JSON, going by its extension.
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Args;
+    use clap::Parser;
+
+    /// A plan ingredient of the given amount or rate.
+    fn ingredient(name: &str, amount: Option<i128>, rate: Option<i128>) -> PlanIngredient {
+        PlanIngredient {
+            name: name.into(),
+            amount: amount.map(Rational::from_integer),
+            rate: rate.map(Rational::from_integer),
+            stack_size: None,
+        }
+    }
+
+    /// The ingredients of a plan.
+    fn ingredients(ingredients: Vec<PlanIngredient>) -> Result<Vec<Ingredient>, String> {
+        Plan {
+            ingredients,
+            ..Plan::default()
+        }
+        .ingredients()
+    }
+
+    /// Save the plan to a file of the given name in a fresh directory, and load it back.
+    fn round_trip(plan: &Plan, file_name: &str) -> Plan {
+        let directory = std::env::temp_dir().join(format!(
+            "factrain-plan-{}-{}",
+            std::process::id(),
+            file_name
+        ));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(file_name);
+        plan.save(&path).unwrap();
+        let loaded = Plan::load(&path);
+        std::fs::remove_dir_all(&directory).unwrap();
+        loaded.unwrap()
+    }
+
+    #[test]
+    fn settings_round_trip_through_plan_files() {
+        let args = Args::try_parse_from([
+            "factrain",
+            "--cars",
+            "2,4",
+            "--dc",
+            "--pure",
+            "--station",
+            "--sides",
+            "2",
+            "--chest",
+            "iron",
+            "iron plate=1200",
+            "steel=300:50",
+        ])
+        .unwrap();
+        let settings = Settings::new(args, &Plan::default()).unwrap();
+        let plan = Plan::from_settings(&settings, &settings.ingredients);
+        assert_eq!(plan.train.sides, Some(2));
+        assert_eq!(plan.train.locomotives, Some(1));
+        assert_eq!(plan.train.train_limit, None);
+
+        for file_name in ["station.toml", "station.json"] {
+            let loaded = round_trip(&plan, file_name);
+            assert_eq!(loaded.chest, Chest::Iron);
+            assert_eq!(loaded.train.cars, vec![2, 4]);
+            assert!(!loaded.train.same_car && loaded.train.different_car && loaded.train.pure);
+            assert!(loaded.output.station);
+            assert_eq!(loaded.train.sides, Some(2));
+
+            let ingredients = loaded.ingredients().unwrap();
+            assert_eq!(ingredients.len(), 2);
+            assert_eq!(ingredients[0].name, "iron plate");
+            assert_eq!(ingredients[0].amount, Rational::from_integer(1200));
+            assert_eq!(ingredients[0].stack_size, 100);
+            assert_eq!(ingredients[1].name, "steel plate");
+            assert_eq!(ingredients[1].stack_size, 50);
+            assert_eq!(loaded.ingredients[0].stack_size, None);
+            assert_eq!(loaded.ingredients[1].stack_size, Some(50));
+        }
+    }
+
+    #[test]
+    fn rates_round_trip_as_rates() {
+        let rates = ingredients(vec![
+            ingredient("iron plate", None, Some(15)),
+            ingredient("copper plate", None, Some(30)),
+        ])
+        .unwrap();
+        assert!(rates.iter().all(|item| item.rate));
+
+        let plan = Plan {
+            ingredients: Plan::plan_ingredients(&rates),
+            ..Plan::default()
+        };
+        assert!(plan
+            .ingredients
+            .iter()
+            .all(|item| item.amount.is_none() && item.rate.is_some()));
+        let loaded = round_trip(&plan, "rates.toml").ingredients().unwrap();
+        assert!(loaded.iter().all(|item| item.rate));
+        assert_eq!(loaded[1].amount, Rational::from_integer(30));
+    }
+
+    #[test]
+    fn merges_ingredients_of_the_same_item() {
+        let merged = ingredients(vec![
+            ingredient("iron plate", Some(1200), None),
+            ingredient("Iron-Plate", Some(300), None),
+            ingredient("steel", Some(100), None),
+        ])
+        .unwrap();
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].amount, Rational::from_integer(1500));
+        assert_eq!(merged[1].name, "steel plate");
+    }
+
+    #[test]
+    fn rejects_invalid_ingredients() {
+        let cases = [
+            (
+                vec![ingredient("iron plate", Some(1), Some(1))],
+                "has both an amount and a rate",
+            ),
+            (
+                vec![ingredient("iron plate", None, None)],
+                "has no amount or rate",
+            ),
+            (
+                vec![
+                    ingredient("iron plate", Some(1), None),
+                    ingredient("copper plate", None, Some(1)),
+                ],
+                "other ingredients have rates",
+            ),
+            (
+                vec![ingredient("unobtainium", Some(1), None)],
+                "has no known stack size",
+            ),
+            (vec![ingredient("iron plate", Some(0), None)], "more than 0"),
+        ];
+        for (plan_ingredients, expected) in cases {
+            let error = ingredients(plan_ingredients).unwrap_err();
+            assert!(error.contains(expected), "{error}");
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_plan_files() {
+        let directory =
+            std::env::temp_dir().join(format!("factrain-plan-{}-invalid", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("invalid.toml");
+        std::fs::write(&path, "ingredients = 3\n").unwrap();
+        let invalid = Plan::load(&path);
+        let missing = Plan::load(&directory.join("missing.toml"));
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert!(invalid.unwrap_err().starts_with("Invalid plan"));
+        assert!(missing.unwrap_err().starts_with("Could not read plan"));
+    }
+}
@@ -0,0 +1,429 @@
+use std::{cmp::Ordering, fmt::Display, str::FromStr};
+
+/// An exact rational number, always kept in lowest terms with a positive denominator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rational {
+    numerator: i128,
+    denominator: i128,
+}
+
+impl Rational {
+    /// Zero as a rational number.
+    pub const ZERO: Rational = Rational {
+        numerator: 0,
+        denominator: 1,
+    };
+
+    /// Create a rational number from a numerator and denominator, reducing it to lowest terms.
+    ///
+    /// # Returns
+    /// The rational number as [Some]\(rational), or [None] if the denominator is zero or the number in lowest terms is too large to represent (e.g. 1 / [i128::MIN]).
+    pub fn new(numerator: i128, denominator: i128) -> Option<Self> {
+        if denominator == 0 {
+            return None;
+        }
+
+        // Reduce the magnitudes, which always fit in a u128, and put the sign back on the numerator.
+        let divisor = gcd(numerator.unsigned_abs(), denominator.unsigned_abs());
+        let magnitude = numerator.unsigned_abs() / divisor;
+        let numerator = match (numerator < 0) != (denominator < 0) {
+            true => 0i128.checked_sub_unsigned(magnitude)?,
+            false => i128::try_from(magnitude).ok()?,
+        };
+        Some(Rational {
+            numerator,
+            denominator: i128::try_from(denominator.unsigned_abs() / divisor).ok()?,
+        })
+    }
+
+    /// Create a rational number from a whole number.
+    pub fn from_integer(integer: i128) -> Self {
+        Rational {
+            numerator: integer,
+            denominator: 1,
+        }
+    }
+
+    /// The nearest [f64] to the rational number.
+    pub fn to_f64(self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    /// The largest whole number less than or equal to the rational number.
+    pub fn floor(self) -> i128 {
+        self.numerator.div_euclid(self.denominator)
+    }
+
+    /// The part of the rational number above its [floor](Rational::floor). This value is between 0 (inclusive) and 1 (exclusive).
+    pub fn fract(self) -> Rational {
+        Rational {
+            numerator: self.numerator.rem_euclid(self.denominator),
+            denominator: self.denominator,
+        }
+    }
+
+    /// Add two rational numbers, returning [None] on overflow.
+    pub fn checked_add(self, other: Rational) -> Option<Rational> {
+        let numerator = self
+            .numerator
+            .checked_mul(other.denominator)?
+            .checked_add(other.numerator.checked_mul(self.denominator)?)?;
+        Rational::new(numerator, self.denominator.checked_mul(other.denominator)?)
+    }
+
+    /// Subtract a rational number from another, returning [None] on overflow.
+    pub fn checked_sub(self, other: Rational) -> Option<Rational> {
+        self.checked_add(Rational {
+            numerator: other.numerator.checked_neg()?,
+            denominator: other.denominator,
+        })
+    }
+
+    /// Multiply two rational numbers, returning [None] on overflow.
+    pub fn checked_mul(self, other: Rational) -> Option<Rational> {
+        Rational::new(
+            self.numerator.checked_mul(other.numerator)?,
+            self.denominator.checked_mul(other.denominator)?,
+        )
+    }
+
+    /// Divide two rational numbers, returning [None] on overflow or division by zero.
+    pub fn checked_div(self, other: Rational) -> Option<Rational> {
+        Rational::new(
+            self.numerator.checked_mul(other.denominator)?,
+            self.denominator.checked_mul(other.numerator)?,
+        )
+    }
+}
+
+impl Ord for Rational {
+    /// Compare two rational numbers exactly, without cross-multiplying, so that numbers of any size are ordered correctly.
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Compare the whole parts, then the fractional parts by comparing their reciprocals the other way around, as in Euclid's algorithm.
+        let (mut a, mut b) = (*self, *other);
+        let mut reversed = false;
+        loop {
+            let ordering = match a.floor().cmp(&b.floor()) {
+                Ordering::Equal => {
+                    let (a_fract, b_fract) = (a.fract(), b.fract());
+                    match (a_fract.numerator, b_fract.numerator) {
+                        (0, 0) => Ordering::Equal,
+                        (0, _) => Ordering::Less,
+                        (_, 0) => Ordering::Greater,
+                        _ => {
+                            a = Rational {
+                                numerator: a_fract.denominator,
+                                denominator: a_fract.numerator,
+                            };
+                            b = Rational {
+                                numerator: b_fract.denominator,
+                                denominator: b_fract.numerator,
+                            };
+                            reversed = !reversed;
+                            continue;
+                        }
+                    }
+                }
+                ordering => ordering,
+            };
+            return match reversed {
+                true => ordering.reverse(),
+                false => ordering,
+            };
+        }
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Rational {
+    /// Format the rational number as an exact decimal if it has one (e.g. "12.5"), or as a fraction otherwise (e.g. "1/3").
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.denominator == 1 {
+            return write!(f, "{}", self.numerator);
+        }
+
+        // Find the power of ten the denominator divides, if any, to write the number out as a decimal.
+        let mut scale: i128 = 10;
+        let mut places = 1;
+        while places <= 18 {
+            if scale % self.denominator == 0 {
+                let Some(scaled) = self.numerator.checked_mul(scale / self.denominator) else {
+                    break;
+                };
+                let sign = if scaled < 0 { "-" } else { "" };
+                let digits = format!("{:0>width$}", scaled.unsigned_abs(), width = places + 1);
+                let (whole, fraction) = digits.split_at(digits.len() - places);
+                return write!(f, "{}{}.{}", sign, whole, fraction.trim_end_matches('0'));
+            }
+            scale *= 10;
+            places += 1;
+        }
+
+        write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+}
+
+/// An error encountered while parsing a [Rational].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseRationalError {
+    /// The input is not a number or fraction.
+    Invalid,
+    /// The input is a fraction with a denominator of zero.
+    ZeroDenominator,
+    /// The input has too many digits to be represented.
+    Overflow,
+}
+
+impl Display for ParseRationalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseRationalError::Invalid => write!(
+                f,
+                "expected a number or fraction (e.g. \"1200\", \"12.5\" or \"1/3\")"
+            ),
+            ParseRationalError::ZeroDenominator => write!(f, "fraction has a denominator of zero"),
+            ParseRationalError::Overflow => write!(f, "number is too large"),
+        }
+    }
+}
+
+impl FromStr for Rational {
+    type Err = ParseRationalError;
+
+    /// Parse a rational number from a whole number ("1200"), a decimal ("12.5") or a fraction of either ("1/3", "2.5/3").
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('/') {
+            Some((numerator, denominator)) => {
+                let numerator = parse_decimal(numerator.trim())?;
+                let denominator = parse_decimal(denominator.trim())?;
+                if denominator.numerator == 0 {
+                    return Err(ParseRationalError::ZeroDenominator);
+                }
+                numerator
+                    .checked_div(denominator)
+                    .ok_or(ParseRationalError::Overflow)
+            }
+            None => parse_decimal(s.trim()),
+        }
+    }
+}
+
+/// Parse an exact rational number from a decimal number such as "-12.5".
+fn parse_decimal(s: &str) -> Result<Rational, ParseRationalError> {
+    let (negative, unsigned) = match s.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+
+    let is_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty()) || !is_digits(whole) || !is_digits(fraction) {
+        return Err(ParseRationalError::Invalid);
+    }
+
+    // Read every digit as one whole number, then scale it back down by the number of decimal places.
+    let mut numerator: i128 = 0;
+    for digit in whole.chars().chain(fraction.chars()) {
+        numerator = numerator
+            .checked_mul(10)
+            .and_then(|numerator| numerator.checked_add(digit.to_digit(10)? as i128))
+            .ok_or(ParseRationalError::Overflow)?;
+    }
+    let denominator = 10i128
+        .checked_pow(fraction.len() as u32)
+        .ok_or(ParseRationalError::Overflow)?;
+
+    let numerator = if negative { -numerator } else { numerator };
+    Rational::new(numerator, denominator).ok_or(ParseRationalError::ZeroDenominator)
+}
+
+/// The greatest common divisor of two whole numbers, or 1 if both are zero.
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.max(1)
+}
+
+impl serde::Serialize for Rational {
+    /// Serialize the rational number as a whole number if it is one, or as a string otherwise (e.g. "12.5" or "1/3"), so that it is never rounded.
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match i64::try_from(self.numerator) {
+            Ok(integer) if self.denominator == 1 => serializer.serialize_i64(integer),
+            _ => serializer.serialize_str(self.to_string().as_str()),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Rational {
+    /// Deserialize a rational number from a whole number, a floating point number, or a string in any form accepted by [Rational::from_str].
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(RationalVisitor)
+    }
+}
+
+/// Visitor deserializing a [Rational] from a number or string.
+struct RationalVisitor;
+
+impl serde::de::Visitor<'_> for RationalVisitor {
+    type Value = Rational;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a number or fraction (e.g. 1200, 12.5 or \"1/3\")")
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(Rational::from_integer(v as i128))
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Rational::from_integer(v as i128))
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        // The shortest decimal representation of the float is what was written in the file.
+        v.to_string().parse().map_err(E::custom)
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        v.parse().map_err(E::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A rational number from a numerator and a non-zero denominator.
+    fn ratio(numerator: i128, denominator: i128) -> Rational {
+        Rational::new(numerator, denominator).unwrap()
+    }
+
+    #[test]
+    fn new_reduces_to_lowest_terms_with_positive_denominator() {
+        assert_eq!(ratio(6, -4), ratio(-3, 2));
+        assert_eq!(ratio(0, 7), Rational::ZERO);
+        assert_eq!(Rational::new(1, 0), None);
+    }
+
+    #[test]
+    fn new_reports_numbers_too_large_to_represent() {
+        assert_eq!(Rational::new(i128::MIN, -1), None);
+        assert_eq!(Rational::new(1, i128::MIN), None);
+        assert_eq!(ratio(i128::MIN, 1), Rational::from_integer(i128::MIN));
+        assert_eq!(ratio(i128::MIN, i128::MIN), Rational::from_integer(1));
+        assert_eq!(ratio(i128::MIN, 2), Rational::from_integer(i128::MIN / 2));
+        assert_eq!(ratio(0, i128::MIN), Rational::ZERO);
+    }
+
+    #[test]
+    fn parses_whole_numbers_decimals_and_fractions() {
+        assert_eq!("1200".parse(), Ok(Rational::from_integer(1200)));
+        assert_eq!("12.5".parse(), Ok(ratio(25, 2)));
+        assert_eq!(".5".parse(), Ok(ratio(1, 2)));
+        assert_eq!("-0.25".parse(), Ok(ratio(-1, 4)));
+        assert_eq!("+3".parse(), Ok(Rational::from_integer(3)));
+        assert_eq!(" 1/3 ".parse(), Ok(ratio(1, 3)));
+        assert_eq!("2.5/3".parse(), Ok(ratio(5, 6)));
+    }
+
+    #[test]
+    fn rejects_invalid_numbers() {
+        for input in ["", ".", "abc", "1.2.3", "1e3", "--1", "1/", "/2"] {
+            assert_eq!(
+                input.parse::<Rational>(),
+                Err(ParseRationalError::Invalid),
+                "{input:?}"
+            );
+        }
+        assert_eq!(
+            "1/0".parse::<Rational>(),
+            Err(ParseRationalError::ZeroDenominator)
+        );
+        assert_eq!(
+            "1/0.0".parse::<Rational>(),
+            Err(ParseRationalError::ZeroDenominator)
+        );
+    }
+
+    #[test]
+    fn rejects_numbers_too_large_to_represent() {
+        let digits = "9".repeat(40);
+        assert_eq!(
+            digits.parse::<Rational>(),
+            Err(ParseRationalError::Overflow)
+        );
+        assert_eq!(
+            format!("1.{digits}").parse::<Rational>(),
+            Err(ParseRationalError::Overflow)
+        );
+    }
+
+    #[test]
+    fn displays_exact_decimals_and_fractions() {
+        assert_eq!(Rational::from_integer(-3).to_string(), "-3");
+        assert_eq!(ratio(5, 2).to_string(), "2.5");
+        assert_eq!(ratio(-1, 8).to_string(), "-0.125");
+        assert_eq!(ratio(1, 1024).to_string(), "0.0009765625");
+        assert_eq!(ratio(1, 3).to_string(), "1/3");
+        assert_eq!(ratio(-7, 6).to_string(), "-7/6");
+    }
+
+    #[test]
+    fn display_round_trips_through_parse() {
+        for value in [ratio(1, 3), ratio(-12345, 100), ratio(7, 40), ratio(22, 7)] {
+            assert_eq!(value.to_string().parse(), Ok(value));
+        }
+    }
+
+    #[test]
+    fn checked_arithmetic_is_exact() {
+        let third = ratio(1, 3);
+        let sixth = ratio(1, 6);
+        assert_eq!(third.checked_add(sixth), Some(ratio(1, 2)));
+        assert_eq!(third.checked_sub(sixth), Some(sixth));
+        assert_eq!(third.checked_mul(sixth), Some(ratio(1, 18)));
+        assert_eq!(third.checked_div(sixth), Some(Rational::from_integer(2)));
+    }
+
+    #[test]
+    fn checked_arithmetic_reports_overflow_and_division_by_zero() {
+        let max = Rational::from_integer(i128::MAX);
+        assert_eq!(max.checked_add(Rational::from_integer(1)), None);
+        assert_eq!(max.checked_mul(Rational::from_integer(2)), None);
+        assert_eq!(
+            Rational::from_integer(i128::MIN).checked_sub(Rational::from_integer(1)),
+            None
+        );
+        assert_eq!(ratio(1, 3).checked_div(Rational::ZERO), None);
+    }
+
+    #[test]
+    fn floor_and_fract_round_towards_negative_infinity() {
+        assert_eq!(ratio(7, 2).floor(), 3);
+        assert_eq!(ratio(7, 2).fract(), ratio(1, 2));
+        assert_eq!(ratio(-7, 2).floor(), -4);
+        assert_eq!(ratio(-7, 2).fract(), ratio(1, 2));
+    }
+
+    #[test]
+    fn orders_by_value() {
+        assert!(ratio(1, 3) < ratio(1, 2));
+        assert!(ratio(-1, 2) < Rational::ZERO);
+        assert!(Rational::from_integer(i128::MAX) > ratio(i128::MAX, 3));
+        // Too large to cross-multiply, and too close to tell apart in floating point.
+        let max = i128::MAX;
+        assert!(ratio(max, max - 1) < ratio(max - 1, max - 2));
+        assert!(ratio(-max, max - 1) > ratio(-(max - 1), max - 2));
+        assert_eq!(
+            ratio(max - 1, max).cmp(&ratio(max - 1, max)),
+            Ordering::Equal
+        );
+        assert!(Rational::from_integer(i128::MIN) < ratio(i128::MIN + 1, 1));
+    }
+}
@@ -0,0 +1,588 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    allocation::{Allocation, Basis},
+    wagon::{self, train_slots, TrainFilters, WAGON_INSERTERS, WAGON_SLOTS},
+    IngredientRatio,
+};
+
+/// A type of chest that a train is unloaded into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Chest {
+    /// Wooden chest, with 16 slots.
+    Wooden,
+    /// Iron chest, with 32 slots.
+    Iron,
+    /// Steel chest, with 48 slots.
+    #[default]
+    Steel,
+}
+
+impl Chest {
+    /// The number of inventory slots in the chest.
+    pub fn slots(&self) -> u32 {
+        match self {
+            Chest::Wooden => 16,
+            Chest::Iron => 32,
+            Chest::Steel => 48,
+        }
+    }
+
+    /// The name of the chest type, as used in labels (e.g. "steel").
+    pub fn name(&self) -> &'static str {
+        match self {
+            Chest::Wooden => "wooden",
+            Chest::Iron => "iron",
+            Chest::Steel => "steel",
+        }
+    }
+
+    /// The prototype name of the chest entity, as used in blueprints (e.g. "steel-chest").
+    pub fn entity_name(&self) -> &'static str {
+        match self {
+            Chest::Wooden => "wooden-chest",
+            Chest::Iron => "iron-chest",
+            Chest::Steel => "steel-chest",
+        }
+    }
+
+    /// The chest type with the given entity prototype name (e.g. "steel-chest"), if any.
+    pub fn from_entity_name(name: &str) -> Option<Chest> {
+        [Chest::Wooden, Chest::Iron, Chest::Steel]
+            .into_iter()
+            .find(|chest| chest.entity_name() == name)
+    }
+}
+
+impl std::str::FromStr for Chest {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wooden" | "wood" => Ok(Chest::Wooden),
+            "iron" => Ok(Chest::Iron),
+            "steel" => Ok(Chest::Steel),
+            _ => Err(format!(
+                "unknown chest type \"{s}\", expected wooden, iron or steel"
+            )),
+        }
+    }
+}
+
+/// The number of chests (and inserters) along one side of an unloading station for a train with `cars` cargo wagons. Each wagon is unloaded by six inserters into six chests, leaving the tiles between wagons empty, as in station blueprints.
+pub fn chests(cars: u32) -> u32 {
+    WAGON_INSERTERS.saturating_mul(cars)
+}
+
+/// The label of a planned train in the output (e.g. "1 car" or "DC 4 cars").
+fn train_label(train: &TrainFilters) -> String {
+    match train.cars {
+        1 => "1 car".into(),
+        _ => train.title(),
+    }
+}
+
+/// The train configurations selected by the user to calculate over.
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    /// The cargo wagon counts of the trains to calculate over.
+    pub cars: Vec<u32>,
+    /// Whether to calculate "SC" (same-car) filtering, where each wagon of a train follows the same filter.
+    pub same_car: bool,
+    /// Whether to calculate "DC" (different-car) filtering, where each wagon of a train is free to follow its own filter.
+    pub different_car: bool,
+    /// Whether to calculate extras, such as a single chest.
+    pub extra: bool,
+    /// The type of chest the train is unloaded into.
+    pub chest: Chest,
+}
+
+/// How much of the output to print.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    /// Only the stacks and inserters of each ingredient.
+    Compact,
+    /// Stacks, items, chests and inserters of each ingredient, and a summary of the ratio error of each configuration.
+    #[default]
+    Normal,
+    /// Everything, including the ratio error of each ingredient and unloading timings.
+    Full,
+}
+
+impl Verbosity {
+    /// Whether the given section is printed at this verbosity.
+    pub fn shows(&self, section: Section) -> bool {
+        match section {
+            Section::Stacks | Section::Inserters => true,
+            Section::Items | Section::Chests => *self >= Verbosity::Normal,
+            Section::Timings | Section::Errors => *self >= Verbosity::Full,
+        }
+    }
+}
+
+impl std::str::FromStr for Verbosity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "compact" => Ok(Verbosity::Compact),
+            "normal" => Ok(Verbosity::Normal),
+            "full" => Ok(Verbosity::Full),
+            _ => Err(format!(
+                "unknown verbosity \"{s}\", expected compact, normal or full"
+            )),
+        }
+    }
+}
+
+/// The format to print the output in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// Human-readable banners and lines.
+    #[default]
+    Text,
+    /// A machine-readable JSON document of everything calculated.
+    Json,
+    /// A CSV table with one row per ingredient and one column per configuration.
+    Csv,
+    /// A Markdown table with one row per ingredient and one column per configuration.
+    Markdown,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "markdown" | "md" => Ok(Format::Markdown),
+            _ => Err(format!(
+                "unknown format \"{s}\", expected text, json, csv or markdown"
+            )),
+        }
+    }
+}
+
+/// A section of the output printed for each ingredient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Section {
+    /// The number of stacks of each ingredient on a train.
+    Stacks,
+    /// The number of items of each ingredient on a train.
+    Items,
+    /// The number of stacks of each ingredient in a row of chests.
+    Chests,
+    /// The number of inserters unloading each ingredient.
+    Inserters,
+    /// The time taken to unload each ingredient.
+    Timings,
+    /// The ratio each configuration actually delivers against the requested ratio.
+    Errors,
+}
+
+impl Section {
+    /// The title of the section, as printed in its banner.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Section::Stacks => "STACKS",
+            Section::Items => "ITEMS",
+            Section::Chests => "CHESTS",
+            Section::Inserters => "INSERTERS",
+            Section::Timings => "TIMINGS",
+            Section::Errors => "ERRORS",
+        }
+    }
+
+    /// The unit that values in this section are counted in.
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Section::Stacks | Section::Chests => "stacks",
+            Section::Items => "items",
+            Section::Inserters => "inserters",
+            Section::Timings => "seconds",
+            Section::Errors => "percent",
+        }
+    }
+}
+
+/// The number of items a stack inserter moves per second between a cargo wagon and a chest, with every inserter capacity bonus researched.
+pub const INSERTER_ITEMS_PER_SECOND: f64 = 27.69;
+
+/// A single configuration calculated over, such as a train or a row of chests.
+#[derive(Debug, Clone)]
+pub struct Row {
+    /// The section of the output this row belongs in.
+    pub section: Section,
+    /// The label of the configuration (e.g. "80 SLOTS (DC 2 cars)").
+    pub label: String,
+    /// The slots of the configuration handed out between ingredients.
+    pub allocation: Allocation,
+}
+
+impl Row {
+    /// The value of the ingredient at `index` in this row, counted in the unit of the row's section.
+    pub fn value(&self, item_ratios: &[IngredientRatio], index: usize) -> u64 {
+        let count = self.allocation.counts[index] as u64;
+        match self.section {
+            Section::Items => count * item_ratios[index].ingredient.stack_size as u64,
+            _ => count,
+        }
+    }
+}
+
+/// The time taken to unload a train through its inserters.
+#[derive(Debug, Clone)]
+pub struct Timing {
+    /// The label of the train and its inserters (e.g. "27 INSERTERS (DC 4 cars)").
+    pub label: String,
+    /// The number of items of each ingredient on the train.
+    pub items: Vec<u64>,
+    /// The number of inserters unloading each ingredient.
+    pub inserters: Vec<u32>,
+}
+
+impl Timing {
+    /// The number of seconds taken to unload the ingredient at `index`, or [None] if no inserters unload it.
+    pub fn seconds(&self, index: usize) -> Option<f64> {
+        match self.inserters[index] {
+            0 if self.items[index] > 0 => None,
+            0 => Some(0.0),
+            inserters => {
+                Some(self.items[index] as f64 / (inserters as f64 * INSERTER_ITEMS_PER_SECOND))
+            }
+        }
+    }
+}
+
+/// Everything calculated for the selected configurations.
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// Every row of the output, in the order they are printed.
+    pub rows: Vec<Row>,
+    /// The unloading timings of each selected train.
+    pub timings: Vec<Timing>,
+}
+
+impl Report {
+    /// Calculate every row and timing of the output for the selected configurations.
+    ///
+    /// # Parameters
+    /// * `item_ratios` - The ingredients to allocate slots to.
+    /// * `selection` - The train configurations to calculate over.
+    /// * `pure` - Whether to dedicate whole wagons to a single ingredient where it fills them, which changes the items beside each wagon's inserters.
+    pub fn new(item_ratios: &[IngredientRatio], selection: &Selection, pure: bool) -> Self {
+        let trains = wagon::plan_trains(item_ratios, selection, pure);
+        Report {
+            rows: build_rows(item_ratios, selection, &trains),
+            timings: build_timings(item_ratios, selection, &trains),
+        }
+    }
+
+    /// The rows that are checked for ratio errors. Every distinct allocation delivers its ratio through the items (or inserters) it holds, so stack rows are left out as they repeat item rows.
+    pub fn checked_rows(&self) -> impl Iterator<Item = &Row> {
+        self.rows
+            .iter()
+            .filter(|row| row.section != Section::Stacks)
+    }
+
+    /// Print the calculated rows for each ingredient, followed by a summary of the error of each configuration, leaving out what the verbosity does not show.
+    ///
+    /// # Parameters
+    /// * `item_ratios` - The ingredients the report was calculated over.
+    /// * `verbosity` - How much of the output to print.
+    pub fn print(&self, item_ratios: &[IngredientRatio], verbosity: Verbosity) {
+        for (index, item) in item_ratios.iter().enumerate() {
+            // Show the exact stack ratio as a fraction alongside its percentage, if calculated.
+            let stack_ratio = match &item.exact {
+                Some(exact) => {
+                    format!("{} = {:.2}%", exact.stack_ratio, item.stack_ratio * 100.0)
+                }
+                None => format!("{:.2}%", item.stack_ratio * 100.0),
+            };
+            println!(
+                "{:=^80}",
+                format!(
+                    " {} x{} (stack {}, {}) ",
+                    item.ingredient.name.to_uppercase(),
+                    item.ingredient.amount,
+                    item.ingredient.stack_size,
+                    stack_ratio
+                )
+            );
+
+            for section in [
+                Section::Stacks,
+                Section::Items,
+                Section::Chests,
+                Section::Inserters,
+            ] {
+                if !verbosity.shows(section) {
+                    continue;
+                }
+
+                println!("{:=^80}", format!(" {} ", section.title()));
+
+                for row in self.rows.iter().filter(|row| row.section == section) {
+                    if section == Section::Chests {
+                        println!(
+                            "{:>40} ---- {} {} stacks ({} items)",
+                            row.label,
+                            row.value(item_ratios, index),
+                            item.ingredient.name,
+                            row.value(item_ratios, index) * item.ingredient.stack_size as u64
+                        );
+                    } else {
+                        println!(
+                            "{:>40} ---- {} {} {}",
+                            row.label,
+                            row.value(item_ratios, index),
+                            item.ingredient.name,
+                            section.unit()
+                        );
+                    }
+                }
+            }
+
+            if verbosity.shows(Section::Timings) {
+                println!("{:=^80}", format!(" {} ", Section::Timings.title()));
+
+                for timing in &self.timings {
+                    match timing.seconds(index) {
+                        Some(seconds) => println!(
+                            "{:>40} ---- {:.1} s to unload {} {} items",
+                            timing.label, seconds, timing.items[index], item.ingredient.name
+                        ),
+                        None => println!(
+                            "{:>40} ---- no inserters to unload {} {} items",
+                            timing.label, timing.items[index], item.ingredient.name
+                        ),
+                    }
+                }
+            }
+
+            if verbosity.shows(Section::Errors) {
+                println!("{:=^80}", format!(" {} ", Section::Errors.title()));
+
+                for row in self.checked_rows() {
+                    let delivered = row.allocation.delivered_ratios(item_ratios)[index];
+                    let deviation = row.allocation.ratio_deviations(item_ratios)[index];
+                    let error = row.allocation.ratio_errors(item_ratios)[index];
+                    println!(
+                        "{:>40} ---- {:.2}% of {:.2}% ({:+.2} pts, {:+.2}%)",
+                        row.label,
+                        delivered * 100.0,
+                        item.ratio * 100.0,
+                        deviation * 100.0,
+                        error * 100.0
+                    );
+                }
+            }
+        }
+
+        if verbosity < Verbosity::Normal || self.checked_rows().next().is_none() {
+            return;
+        }
+
+        println!("{:=^80}", " RATIO ERROR ");
+
+        for row in self.checked_rows() {
+            let limiting = match row.allocation.limiting_ingredient(item_ratios) {
+                Some(index) => item_ratios[index].ingredient.name.as_str(),
+                None => "none",
+            };
+
+            match row.allocation.basis {
+                Basis::Stacks => println!(
+                    "{:>40} ---- {:.2}% max error, {:.1} wasted slots, {} limiting",
+                    row.label,
+                    row.allocation.max_ratio_error(item_ratios) * 100.0,
+                    row.allocation.wasted_slots(item_ratios),
+                    limiting
+                ),
+                Basis::Items => println!(
+                    "{:>40} ---- {:.2}% max error",
+                    row.label,
+                    row.allocation.max_ratio_error(item_ratios) * 100.0
+                ),
+            }
+        }
+    }
+}
+
+/// Build every row of the output for the selected configurations, in the order they are printed. Inserters are allocated along the unloading station of each planned train.
+fn build_rows(
+    item_ratios: &[IngredientRatio],
+    selection: &Selection,
+    trains: &[TrainFilters],
+) -> Vec<Row> {
+    let mut rows: Vec<Row> = Vec::new();
+    let mut push = |section: Section, label: String, allocation: Allocation| {
+        rows.push(Row {
+            section,
+            label,
+            allocation,
+        })
+    };
+    let selected = |cars: u32| selection.cars.contains(&cars);
+    let chest = selection.chest;
+    let chest_slots = |cars: u32| chest.slots() * chests(cars);
+
+    // Stacks.
+    if selected(1) || selection.same_car {
+        push(
+            Section::Stacks,
+            "40 SLOTS (1 car)".into(),
+            Allocation::new(item_ratios, WAGON_SLOTS),
+        );
+    }
+
+    for &cars in selection.cars.iter().filter(|cars| **cars > 1) {
+        if selection.different_car {
+            push(
+                Section::Stacks,
+                format!("{} SLOTS ({} cars)", train_slots(cars), cars),
+                Allocation::new(item_ratios, train_slots(cars)),
+            );
+        }
+    }
+
+    // Items.
+    if selected(1) {
+        push(
+            Section::Items,
+            "40 SLOTS (1 car)".into(),
+            Allocation::new(item_ratios, WAGON_SLOTS),
+        );
+    }
+
+    for &cars in selection.cars.iter().filter(|cars| **cars > 1) {
+        if selection.same_car {
+            // "SC" refers to "same car" filtering. This requires each train car to follow the same filter.
+            push(
+                Section::Items,
+                format!("{} SLOTS (SC {} cars)", train_slots(cars), cars),
+                Allocation::repeated(item_ratios, WAGON_SLOTS, cars),
+            );
+        }
+
+        if selection.different_car {
+            // "DC" refers to "different car" filtering. This allows each train car to follow their own unique filter.
+            push(
+                Section::Items,
+                format!("{} SLOTS (DC {} cars)", train_slots(cars), cars),
+                Allocation::new(item_ratios, train_slots(cars)),
+            );
+        }
+    }
+
+    // Chests.
+    if selection.extra {
+        push(
+            Section::Chests,
+            format!("{} SLOTS (1 {} chest)", chest.slots(), chest.name()),
+            Allocation::new(item_ratios, chest.slots()),
+        );
+    }
+
+    for &cars in &selection.cars {
+        push(
+            Section::Chests,
+            format!(
+                "{} SLOTS ({} {} chests)",
+                chest_slots(cars),
+                chests(cars),
+                chest.name()
+            ),
+            Allocation::new(item_ratios, chest_slots(cars)),
+        );
+    }
+
+    // Inserters.
+    for train in trains {
+        push(
+            Section::Inserters,
+            format!("{} INSERTERS ({})", chests(train.cars), train_label(train)),
+            Allocation::unloading(item_ratios, train),
+        );
+    }
+
+    rows
+}
+
+/// Build the unloading timings of each selected train length from its planned trains. Trains are timed with DC filtering if it is selected, and SC filtering otherwise.
+fn build_timings(
+    item_ratios: &[IngredientRatio],
+    selection: &Selection,
+    trains: &[TrainFilters],
+) -> Vec<Timing> {
+    selection
+        .cars
+        .iter()
+        .filter_map(|&cars| {
+            let same_car = cars == 1 || !selection.different_car;
+            let train = trains
+                .iter()
+                .find(|train| train.cars == cars && train.same_car == same_car)?;
+
+            Some(Timing {
+                label: format!("{} INSERTERS ({})", chests(cars), train_label(train)),
+                items: (0..item_ratios.len())
+                    .map(|index| {
+                        train.count(index) as u64 * item_ratios[index].ingredient.stack_size as u64
+                    })
+                    .collect(),
+                inserters: train.inserters(item_ratios),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_verbosity_shows_more_than_the_last() {
+        let shown = |verbosity: Verbosity| -> Vec<Section> {
+            [
+                Section::Stacks,
+                Section::Items,
+                Section::Chests,
+                Section::Inserters,
+                Section::Timings,
+                Section::Errors,
+            ]
+            .into_iter()
+            .filter(|section| verbosity.shows(*section))
+            .collect()
+        };
+        assert_eq!(
+            shown(Verbosity::Compact),
+            vec![Section::Stacks, Section::Inserters]
+        );
+        assert_eq!(
+            shown(Verbosity::Normal),
+            vec![
+                Section::Stacks,
+                Section::Items,
+                Section::Chests,
+                Section::Inserters
+            ]
+        );
+        assert_eq!(shown(Verbosity::Full).len(), 6);
+    }
+
+    #[test]
+    fn parses_verbosities() {
+        assert_eq!("compact".parse(), Ok(Verbosity::Compact));
+        assert_eq!("normal".parse(), Ok(Verbosity::Normal));
+        assert_eq!("full".parse(), Ok(Verbosity::Full));
+        assert!("verbose".parse::<Verbosity>().is_err());
+    }
+}
@@ -0,0 +1,319 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    blueprint::{self, Signal, StationRole},
+    wagon::TrainFilters,
+    IngredientRatio,
+};
+
+/// The name of the station trains wait at between deliveries when using interrupts.
+const DEPOT_STATION: &str = "Depot";
+
+/// The number of ticks a train waits at the depot without activity before checking its interrupts again (5 seconds).
+const DEPOT_INACTIVITY_TICKS: u32 = 300;
+
+/// How long a train waits at its loading station.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoadWait {
+    /// Until every filtered slot of every wagon is full.
+    Full,
+    /// Until the train holds at least its planned count of each item.
+    #[default]
+    Counts,
+}
+
+impl std::str::FromStr for LoadWait {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(LoadWait::Full),
+            "counts" => Ok(LoadWait::Counts),
+            _ => Err(format!(
+                "unknown wait condition \"{s}\", expected full or counts"
+            )),
+        }
+    }
+}
+
+/// The schedule of the locomotives of a blueprinted train.
+#[derive(Debug, Clone, Serialize)]
+pub struct TrainSchedule {
+    /// The entity numbers of the locomotives following the schedule.
+    pub locomotives: Vec<u32>,
+    /// The schedule.
+    pub schedule: Schedule,
+}
+
+/// A train schedule, in the form of the game version it is written for.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Schedule {
+    /// A Factorio 1.1 schedule, which is a list of stations.
+    Records(Vec<Record>),
+    /// A Factorio 2.0 schedule, with interrupts alongside the list of stations.
+    Interrupts {
+        /// The stations of the schedule.
+        records: Vec<Record>,
+        /// The interrupts of the schedule.
+        interrupts: Vec<Interrupt>,
+    },
+}
+
+/// A station of a train schedule, and the conditions to wait for before leaving it.
+#[derive(Debug, Clone, Serialize)]
+pub struct Record {
+    /// The name of the station.
+    pub station: String,
+    /// The conditions to wait for.
+    pub wait_conditions: Vec<WaitCondition>,
+}
+
+/// A condition a train waits for at a station, or an interrupt checks for.
+#[derive(Debug, Clone, Serialize)]
+pub struct WaitCondition {
+    /// The type of condition (e.g. "full", "empty" or "item_count").
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// How the condition combines with the one before it: "and" or "or".
+    pub compare_type: &'static str,
+    /// The number of ticks to wait, for time conditions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ticks: Option<u32>,
+    /// The circuit condition, for item count conditions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<Condition>,
+}
+
+/// A circuit condition comparing a signal to a constant.
+#[derive(Debug, Clone, Serialize)]
+pub struct Condition {
+    /// The signal compared.
+    pub first_signal: Signal,
+    /// The constant the signal is compared to.
+    pub constant: i32,
+    /// The comparison (e.g. "≥").
+    pub comparator: &'static str,
+}
+
+/// A Factorio 2.0 schedule interrupt, sending a train to its targets whenever its conditions are met.
+#[derive(Debug, Clone, Serialize)]
+pub struct Interrupt {
+    /// The name of the interrupt.
+    pub name: String,
+    /// The conditions that trigger the interrupt.
+    pub conditions: Vec<WaitCondition>,
+    /// The stations to visit when triggered.
+    pub targets: Vec<Record>,
+    /// Whether the interrupt can trigger while another interrupt is being followed.
+    pub inside_interrupt: bool,
+}
+
+impl WaitCondition {
+    /// A condition with nothing but its type set, combined with "or".
+    fn new(kind: &'static str) -> Self {
+        WaitCondition {
+            kind,
+            compare_type: "or",
+            ticks: None,
+            condition: None,
+        }
+    }
+}
+
+/// The conditions of a train holding at least its planned count of each item ("each item >= N"), combined with "and", with items named as in Factorio 2.0 if `factorio_2`.
+fn item_count_conditions(
+    item_ratios: &[IngredientRatio],
+    train: &TrainFilters,
+    factorio_2: bool,
+) -> Vec<WaitCondition> {
+    blueprint::item_signals(item_ratios, factorio_2, |index| train.count(index))
+        .into_iter()
+        .map(|(signal, count)| WaitCondition {
+            compare_type: "and",
+            condition: Some(Condition {
+                first_signal: signal,
+                constant: count,
+                comparator: "≥",
+            }),
+            ..WaitCondition::new("item_count")
+        })
+        .collect()
+}
+
+/// Build the schedule of a train delivering its planned load from its loading station to its unloading station.
+///
+/// Without interrupts, the train travels between the two stations, waiting at the loading station until it is loaded and at the unloading station until it is empty. With interrupts (Factorio 2.0), the train waits at a depot, and is sent to load whenever it is empty and to unload whenever it is loaded.
+///
+/// # Parameters
+/// * `item_ratios` - The ingredients the filters were planned for.
+/// * `train` - The planned filters of every wagon of the train.
+/// * `locomotives` - The number of locomotives at the front of the train, numbered first in its blueprint.
+/// * `wait` - How long the train waits at its loading station.
+/// * `interrupts` - Whether to write the schedule as Factorio 2.0 interrupts, naming items as in Factorio 2.0.
+pub fn train_schedule(
+    item_ratios: &[IngredientRatio],
+    train: &TrainFilters,
+    locomotives: u32,
+    wait: LoadWait,
+    interrupts: bool,
+) -> TrainSchedule {
+    let loaded = match wait {
+        LoadWait::Full => vec![WaitCondition::new("full")],
+        LoadWait::Counts => item_count_conditions(item_ratios, train, interrupts),
+    };
+
+    let load = Record {
+        station: blueprint::station_name(item_ratios, train.cars, StationRole::Pickup, interrupts),
+        wait_conditions: loaded.clone(),
+    };
+    let unload = Record {
+        station: blueprint::station_name(item_ratios, train.cars, StationRole::Drop, interrupts),
+        wait_conditions: vec![WaitCondition::new("empty")],
+    };
+
+    let schedule = if interrupts {
+        Schedule::Interrupts {
+            records: vec![Record {
+                station: DEPOT_STATION.into(),
+                wait_conditions: vec![WaitCondition {
+                    ticks: Some(DEPOT_INACTIVITY_TICKS),
+                    ..WaitCondition::new("inactivity")
+                }],
+            }],
+            interrupts: vec![
+                Interrupt {
+                    name: format!("Load when empty ({})", train.title()),
+                    conditions: vec![WaitCondition::new("empty")],
+                    targets: vec![load],
+                    inside_interrupt: false,
+                },
+                Interrupt {
+                    name: format!("Unload when loaded ({})", train.title()),
+                    conditions: loaded,
+                    targets: vec![unload],
+                    inside_interrupt: false,
+                },
+            ],
+        }
+    } else {
+        Schedule::Records(vec![load, unload])
+    };
+
+    TrainSchedule {
+        locomotives: (1..=locomotives).collect(),
+        schedule,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        calculate_ratios,
+        rational::Rational,
+        report::{Chest, Selection},
+        wagon, Ingredient,
+    };
+    use serde_json::json;
+
+    /// The schedule of a 2-car SC train of 30 stacks of iron plate to 10 of empty barrels per wagon, as written into its blueprint.
+    fn schedule(wait: LoadWait, interrupts: bool) -> serde_json::Value {
+        let ingredients = vec![
+            Ingredient::new("iron plate", Rational::from_integer(300), 100).unwrap(),
+            Ingredient::new("empty barrel", Rational::from_integer(100), 100).unwrap(),
+        ];
+        let item_ratios = calculate_ratios(&ingredients, false).unwrap();
+        let selection = Selection {
+            cars: vec![2],
+            same_car: true,
+            different_car: false,
+            extra: false,
+            chest: Chest::Steel,
+        };
+        let train = &wagon::plan_trains(&item_ratios, &selection, false)[0];
+        serde_json::to_value(train_schedule(&item_ratios, train, 2, wait, interrupts)).unwrap()
+    }
+
+    /// An item count condition of at least `count` of an item.
+    fn at_least(name: &str, count: i32) -> serde_json::Value {
+        json!({
+            "type": "item_count",
+            "compare_type": "and",
+            "condition": {
+                "first_signal": { "type": "item", "name": name },
+                "constant": count,
+                "comparator": "≥",
+            },
+        })
+    }
+
+    #[test]
+    fn waits_for_the_planned_counts() {
+        let schedule = schedule(LoadWait::Counts, false);
+        assert_eq!(schedule["locomotives"], json!([1, 2]));
+        let records = schedule["schedule"].as_array().unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(records[0]["station"]
+            .as_str()
+            .unwrap()
+            .ends_with("Pickup 2-car"));
+        assert_eq!(
+            records[0]["wait_conditions"],
+            json!([at_least("iron-plate", 6000), at_least("empty-barrel", 2000)])
+        );
+        assert!(records[1]["station"]
+            .as_str()
+            .unwrap()
+            .ends_with("Drop 2-car"));
+        assert_eq!(
+            records[1]["wait_conditions"],
+            json!([{ "type": "empty", "compare_type": "or" }])
+        );
+    }
+
+    #[test]
+    fn waits_for_full_cargo() {
+        let schedule = schedule(LoadWait::Full, false);
+        assert_eq!(
+            schedule["schedule"][0]["wait_conditions"],
+            json!([{ "type": "full", "compare_type": "or" }])
+        );
+    }
+
+    #[test]
+    fn interrupts_send_trains_from_the_depot() {
+        let schedule = &schedule(LoadWait::Counts, true)["schedule"];
+        assert_eq!(
+            schedule["records"],
+            json!([{
+                "station": DEPOT_STATION,
+                "wait_conditions": [{ "type": "inactivity", "compare_type": "or", "ticks": 300 }],
+            }])
+        );
+
+        let interrupts = schedule["interrupts"].as_array().unwrap();
+        assert_eq!(interrupts.len(), 2);
+        assert_eq!(
+            interrupts[0]["conditions"],
+            json!([{ "type": "empty", "compare_type": "or" }])
+        );
+        assert!(interrupts[0]["targets"][0]["station"]
+            .as_str()
+            .unwrap()
+            .contains("[item=barrel]"));
+        assert_eq!(
+            interrupts[1]["conditions"],
+            json!([at_least("iron-plate", 6000), at_least("barrel", 2000)])
+        );
+        assert_eq!(interrupts[1]["inside_interrupt"], false);
+    }
+
+    #[test]
+    fn parses_wait_conditions() {
+        assert_eq!("full".parse(), Ok(LoadWait::Full));
+        assert_eq!("counts".parse(), Ok(LoadWait::Counts));
+        assert!("empty".parse::<LoadWait>().is_err());
+    }
+}
@@ -0,0 +1,611 @@
+use std::path::PathBuf;
+
+use crate::{
+    amount::{Amount, AmountInput},
+    args::Settings,
+    check_rate, data, merge_ingredient, parse_ingredient,
+    plan::Plan,
+    print_output, prompt_ingredients, prompt_or_sigint,
+    rational::Rational,
+    save_plan,
+    wagon::MAX_CARS,
+    Ingredient, LineEditor,
+};
+
+/// The prompt of the interactive session.
+const SESSION_PROMPT: &str = "factrain> ";
+
+/// The commands of the interactive session, as printed by [SessionCommand::Help].
+const SESSION_HELP: &[(&str, &str)] = &[
+    ("add", "prompt for more ingredients"),
+    ("add NAME AMOUNT[:STACK]", "add an ingredient"),
+    (
+        "set NAME AMOUNT",
+        "change the amount of an ingredient (e.g. 12 stacks or 15/s)",
+    ),
+    ("rm NAME", "remove an ingredient"),
+    ("list", "list the ingredients"),
+    ("cars N[,N...]", "set the cargo wagon counts of the trains"),
+    ("mode sc|dc|both", "set the wagon filtering"),
+    ("calc", "calculate and print the ratios"),
+    ("save NAME", "save the ingredients and trains as a session"),
+    (
+        "load NAME",
+        "replace the ingredients and trains with a saved session",
+    ),
+    ("sessions", "list the saved sessions"),
+    ("help", "print this list of commands"),
+    ("quit", "exit (or press CTRL-C or CTRL-D)"),
+];
+
+/// A command typed into the interactive session.
+#[derive(Debug, Clone)]
+pub enum SessionCommand {
+    /// Prompt for more ingredients, one field at a time.
+    Prompt,
+    /// Add an ingredient.
+    Add(Ingredient),
+    /// Change the amount of the ingredient with the given name.
+    Set {
+        /// The name of the ingredient.
+        name: String,
+        /// The new amount of the ingredient, as typed.
+        amount: AmountInput,
+    },
+    /// Remove the ingredient with the given name.
+    Remove(String),
+    /// List the ingredients.
+    List,
+    /// Set the cargo wagon counts of the trains.
+    Cars(Vec<u32>),
+    /// Set the wagon filtering.
+    Mode {
+        /// Whether to calculate same-car ("SC") filtering.
+        same_car: bool,
+        /// Whether to calculate different-car ("DC") filtering.
+        different_car: bool,
+    },
+    /// Calculate and print the ratios.
+    Calc,
+    /// Save the ingredients and settings under the given session name.
+    Save(String),
+    /// Replace the ingredients and train settings with those of the session saved under the given name.
+    Load(String),
+    /// List the names of the saved sessions.
+    Sessions,
+    /// Print the list of commands.
+    Help,
+    /// End the session.
+    Quit,
+}
+
+/// Split the arguments of a command into an item name and the last word after it (e.g. "iron plate 1200" into "iron plate" and "1200"), as item names can contain spaces.
+fn split_last_word(args: &str) -> Option<(&str, &str)> {
+    args.trim()
+        .rsplit_once(char::is_whitespace)
+        .map(|(name, word)| (name.trim(), word))
+}
+
+/// Split the arguments of a command into an item name and the amount typed after it (e.g. "iron plate 12 stacks" into "iron plate" and 12 stacks), taking as many words from the end as make an amount, as both item names and amounts can contain spaces.
+///
+/// # Returns
+/// The name and amount as [Ok]\((name, amount)), or [Err]\(message) if no words at the end make an amount.
+fn split_amount(args: &str) -> Result<(&str, AmountInput), String> {
+    let args = args.trim();
+    let mut error = String::from("expected set NAME AMOUNT");
+    for (index, _) in args.char_indices().filter(|(_, c)| c.is_whitespace()) {
+        let name = args[..index].trim();
+        if name.is_empty() {
+            continue;
+        }
+        match args[index..].parse::<AmountInput>() {
+            Ok(amount) => return Ok((name, amount)),
+            // The fewest words tried last are the likeliest to be the amount meant.
+            Err(e) => error = format!("invalid amount: {e}"),
+        }
+    }
+    Err(error)
+}
+
+/// Parse an ingredient typed in the form "NAME AMOUNT" or "NAME AMOUNT:STACK" (e.g. "iron plate 1200"), where STACK overrides the stack size of the item.
+///
+/// # Returns
+/// The ingredient as [Ok]\(ingredient), or [Err]\(message) describing why it is invalid.
+pub fn parse_ingredient_words(words: &str) -> Result<Ingredient, String> {
+    let (name, value) = split_last_word(words).ok_or("expected NAME AMOUNT[:STACK]")?;
+    match value.split_once(':') {
+        Some((amount, stack_size)) => parse_ingredient(name, amount, Some(stack_size)),
+        None => parse_ingredient(name, value, None),
+    }
+}
+
+impl std::str::FromStr for SessionCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (command, args) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        let args = args.trim();
+
+        match (command.to_lowercase().as_str(), args.is_empty()) {
+            ("add", true) => Ok(SessionCommand::Prompt),
+            ("add", false) => Ok(SessionCommand::Add(parse_ingredient_words(args)?)),
+            ("set", false) => {
+                let (name, amount) = split_amount(args)?;
+                Ok(SessionCommand::Set {
+                    name: data::canonical_item_name(name),
+                    amount,
+                })
+            }
+            ("rm" | "remove", false) => Ok(SessionCommand::Remove(data::canonical_item_name(args))),
+            ("list" | "ls", true) => Ok(SessionCommand::List),
+            ("cars", false) => {
+                let cars = args
+                    .split(',')
+                    .map(|cars| match cars.trim().parse::<u32>() {
+                        Ok(cars) if (1..=MAX_CARS).contains(&cars) => Ok(cars),
+                        _ => Err(format!(
+                            "invalid cargo wagon count \"{}\", expected a number from 1 to {MAX_CARS}",
+                            cars.trim()
+                        )),
+                    })
+                    .collect::<Result<Vec<u32>, String>>()?;
+                Ok(SessionCommand::Cars(cars))
+            }
+            ("mode", false) => match args.to_lowercase().as_str() {
+                "sc" => Ok(SessionCommand::Mode {
+                    same_car: true,
+                    different_car: false,
+                }),
+                "dc" => Ok(SessionCommand::Mode {
+                    same_car: false,
+                    different_car: true,
+                }),
+                "both" => Ok(SessionCommand::Mode {
+                    same_car: true,
+                    different_car: true,
+                }),
+                _ => Err(format!("unknown mode \"{args}\", expected sc, dc or both")),
+            },
+            ("calc", true) => Ok(SessionCommand::Calc),
+            ("save", false) => Ok(SessionCommand::Save(args.to_string())),
+            ("load", false) => Ok(SessionCommand::Load(args.to_string())),
+            ("sessions", true) => Ok(SessionCommand::Sessions),
+            ("help" | "?", true) => Ok(SessionCommand::Help),
+            ("quit" | "exit", true) => Ok(SessionCommand::Quit),
+            ("set" | "rm" | "remove" | "cars" | "mode" | "save" | "load", true) => Err(format!(
+                "\"{command}\" needs arguments, type help for usage"
+            )),
+            ("list" | "ls" | "calc" | "sessions" | "help" | "?" | "quit" | "exit", false) => {
+                Err(format!("\"{command}\" takes no arguments"))
+            }
+            _ => Err(format!(
+                "unknown command \"{command}\", type help for a list of commands"
+            )),
+        }
+    }
+}
+
+/// The directory input history and saved sessions are kept in, under the user's data directory (e.g. "~/.local/share/factrain" on Linux), or [None] if the user has no data directory.
+fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("factrain"))
+}
+
+/// The file input history is kept in between runs, or [None] if the user has no data directory.
+pub fn history_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("history.txt"))
+}
+
+/// The directory sessions are saved in.
+///
+/// # Returns
+/// The directory as [Ok]\(path), or [Err]\(message) if the user has no data directory.
+fn sessions_dir() -> Result<PathBuf, String> {
+    data_dir()
+        .map(|dir| dir.join("sessions"))
+        .ok_or("No data directory was found to keep sessions in.".into())
+}
+
+/// The plan file a session is saved in.
+///
+/// # Returns
+/// The file as [Ok]\(path), or [Err]\(message) if the name is not a valid session name or the user has no data directory.
+fn session_path(name: &str) -> Result<PathBuf, String> {
+    let valid = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!(
+            "invalid session name \"{name}\", use only letters, digits, \"-\" and \"_\""
+        ));
+    }
+
+    Ok(sessions_dir()?.join(format!("{name}.toml")))
+}
+
+/// Save the ingredients and settings of the session as a plan, under the given session name.
+///
+/// # Returns
+/// The file the session was saved in as [Ok]\(path), or [Err]\(message) if it could not be saved.
+fn save_session(
+    name: &str,
+    settings: &Settings,
+    ingredients: &[Ingredient],
+) -> Result<PathBuf, String> {
+    let path = session_path(name)?;
+    std::fs::create_dir_all(sessions_dir()?)
+        .map_err(|e| format!("Could not create the sessions directory: {e}"))?;
+    Plan::from_settings(settings, ingredients).save(&path)?;
+    Ok(path)
+}
+
+/// Load the session saved under the given name, replacing the ingredients and the train settings (cargo wagon counts, wagon filtering and chest) of the session. Output settings are kept from the running session.
+///
+/// # Returns
+/// [Ok] if the session was loaded, or [Err]\(message) if it does not exist or is not a valid plan, in which case nothing is replaced.
+fn load_session(
+    name: &str,
+    settings: &mut Settings,
+    ingredients: &mut Vec<Ingredient>,
+) -> Result<(), String> {
+    let path = session_path(name)?;
+    if !path.exists() {
+        return Err(format!(
+            "No session is saved as \"{name}\". Type sessions to list the saved sessions."
+        ));
+    }
+
+    let plan = Plan::load(&path)?;
+    *ingredients = plan.ingredients()?;
+    settings.selection.cars = plan.train.cars;
+    settings.selection.same_car = plan.train.same_car;
+    settings.selection.different_car = plan.train.different_car;
+    settings.selection.chest = plan.chest;
+    settings.pure = plan.train.pure;
+    Ok(())
+}
+
+/// Print the names of the saved sessions, in alphabetical order.
+fn print_sessions() {
+    let mut names: Vec<String> = sessions_dir()
+        .ok()
+        .and_then(|dir| std::fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            match path.extension()?.to_str()? {
+                "toml" => Some(path.file_stem()?.to_str()?.to_string()),
+                _ => None,
+            }
+        })
+        .collect();
+    names.sort();
+
+    println!("{:=^80}", " SESSIONS ");
+    if names.is_empty() {
+        println!("No sessions are saved yet. Save one with save.");
+    }
+    for name in names {
+        println!("{name}");
+    }
+}
+
+/// Write the input history to its file, so that it can be recalled on the next run.
+pub fn save_history(rl_editor: &mut LineEditor) {
+    let Some(path) = history_path() else {
+        return;
+    };
+
+    let saved = match path.parent() {
+        Some(dir) => std::fs::create_dir_all(dir).map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
+    .and_then(|()| rl_editor.save_history(&path).map_err(|e| e.to_string()));
+    if let Err(e) = saved {
+        eprintln!("Could not save input history \"{}\": {e}", path.display());
+    }
+}
+
+/// Change the amount of the ingredient with the given name, converting stacks to items with its stack size.
+///
+/// # Returns
+/// The new amount as [Ok]\(amount), or [Err]\(message) if there is no such ingredient, or the amount is too large, not more than 0, or a rate where the other ingredients are amounts (or the other way round).
+fn set_amount(
+    ingredients: &mut [Ingredient],
+    name: &str,
+    amount: AmountInput,
+) -> Result<Amount, String> {
+    let index = ingredients
+        .iter()
+        .position(|item| item.name == name)
+        .ok_or(format!("\"{name}\" is not an ingredient. Add it with add."))?;
+    let amount = amount
+        .to_amount(ingredients[index].stack_size)
+        .ok_or("The amount is too large.")?;
+    if amount.value <= Rational::ZERO {
+        return Err("The amount must be more than 0. Remove it with rm instead.".into());
+    }
+    check_rate(ingredients, index, amount.rate)?;
+
+    let item = &mut ingredients[index];
+    item.amount = amount.value;
+    item.rate = amount.rate;
+    Ok(amount)
+}
+
+/// Print the ingredients of the session, with their amounts and stack sizes.
+fn print_ingredients(ingredients: &[Ingredient]) {
+    println!("{:=^80}", " INGREDIENTS ");
+    if ingredients.is_empty() {
+        println!("No ingredients yet. Add some with add.");
+    }
+    for ingredient in ingredients {
+        println!(
+            "{:>40} ---- {} (stack size {})",
+            ingredient.name, ingredient.amount, ingredient.stack_size
+        );
+    }
+}
+
+/// Calculate and print the ratios of the session's ingredients with its settings, saving the plan first if requested. Problems are printed rather than ending the session.
+fn calculate(settings: &Settings, ingredients: &[Ingredient]) {
+    if ingredients.is_empty() {
+        println!("No ingredients were given.");
+        return;
+    }
+
+    if let Err(e) = settings.validate() {
+        println!("{e}");
+        return;
+    }
+
+    if let Err(e) = save_plan(settings, ingredients) {
+        println!("{e}");
+    }
+
+    if let Err(e) = print_output(settings, ingredients) {
+        println!("{e}");
+    }
+}
+
+/// Run an interactive session over the ingredients prompted for, printing their ratios first and then reading commands to change the ingredients and train settings and recalculate, until the user quits.
+///
+/// # Parameters
+/// * `rl_editor` - Rustyline [Editor](rustyline::Editor) used to read user's input.
+/// * `settings` - The settings to calculate with, changed by the session's commands.
+/// * `ingredients` - The ingredients prompted for before the session.
+pub fn run_session(
+    rl_editor: &mut LineEditor,
+    mut settings: Settings,
+    mut ingredients: Vec<Ingredient>,
+) {
+    calculate(&settings, &ingredients);
+    println!("Type help for a list of commands to change the ingredients and recalculate.");
+
+    while let Some(Some(line)) = prompt_or_sigint(rl_editor, SESSION_PROMPT) {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let command = match line.parse::<SessionCommand>() {
+            Ok(command) => command,
+            Err(e) => {
+                println!("{e}");
+                continue;
+            }
+        };
+
+        match command {
+            SessionCommand::Prompt => prompt_ingredients(rl_editor, &mut ingredients),
+            SessionCommand::Add(ingredient) => {
+                let name = ingredient.name.clone();
+                match merge_ingredient(&mut ingredients, ingredient) {
+                    Ok(true) => println!(
+                        "\"{name}\" was already an ingredient, so its amounts were added together."
+                    ),
+                    Ok(false) => {}
+                    Err(e) => println!("{e}"),
+                }
+            }
+            SessionCommand::Set { name, amount } => {
+                match set_amount(&mut ingredients, &name, amount) {
+                    Ok(amount) => println!("= {amount}"),
+                    Err(e) => println!("{e}"),
+                }
+            }
+            SessionCommand::Remove(name) => {
+                let count = ingredients.len();
+                ingredients.retain(|item| item.name != name);
+                if ingredients.len() == count {
+                    println!("\"{name}\" is not an ingredient.");
+                }
+            }
+            SessionCommand::List => print_ingredients(&ingredients),
+            SessionCommand::Cars(cars) => {
+                settings.selection.cars = cars;
+                settings.selection.cars.sort();
+                settings.selection.cars.dedup();
+                // An explicit train length replaces comparing train lengths.
+                settings.compare = None;
+            }
+            SessionCommand::Mode {
+                same_car,
+                different_car,
+            } => {
+                settings.selection.same_car = same_car;
+                settings.selection.different_car = different_car;
+            }
+            SessionCommand::Calc => calculate(&settings, &ingredients),
+            SessionCommand::Save(name) => match save_session(&name, &settings, &ingredients) {
+                Ok(path) => println!("Saved session \"{name}\" to \"{}\".", path.display()),
+                Err(e) => println!("{e}"),
+            },
+            SessionCommand::Load(name) => {
+                match load_session(&name, &mut settings, &mut ingredients) {
+                    Ok(()) => {
+                        println!("Loaded session \"{name}\".");
+                        print_ingredients(&ingredients);
+                    }
+                    Err(e) => println!("{e}"),
+                }
+            }
+            SessionCommand::Sessions => print_sessions(),
+            SessionCommand::Help => {
+                for (usage, description) in SESSION_HELP {
+                    println!("{:>40} ---- {}", usage, description);
+                }
+            }
+            SessionCommand::Quit => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Args;
+    use clap::Parser;
+
+    /// Parse a set command into the name and amount it sets.
+    fn parse_set(command: &str) -> Result<(String, AmountInput), String> {
+        match command.parse::<SessionCommand>()? {
+            SessionCommand::Set { name, amount } => Ok((name, amount)),
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn set_accepts_the_amount_grammar() {
+        let mut ingredients = vec![
+            Ingredient::new("iron plate", Rational::from_integer(1200), 100).unwrap(),
+            Ingredient::new("steel plate", Rational::from_integer(300), 100).unwrap(),
+        ];
+        for (command, items) in [
+            ("set iron plate 12 stacks", 1200),
+            ("set Iron-Plate 3*450", 1350),
+            ("set iron plate 2.5k", 2500),
+            ("set iron plate (1200 + 600) / 2", 900),
+        ] {
+            let (name, amount) = parse_set(command).unwrap();
+            assert_eq!(name, "iron plate");
+            let amount = set_amount(&mut ingredients, &name, amount).unwrap();
+            assert_eq!(amount.value, Rational::from_integer(items), "{command}");
+            assert_eq!(ingredients[0].amount, Rational::from_integer(items));
+        }
+    }
+
+    #[test]
+    fn set_keeps_rates_and_amounts_apart() {
+        let mut ingredients = vec![
+            Ingredient::new("iron plate", Rational::from_integer(1200), 100).unwrap(),
+            Ingredient::new("steel plate", Rational::from_integer(300), 100).unwrap(),
+        ];
+        let (name, amount) = parse_set("set iron plate 15/s").unwrap();
+        assert!(set_amount(&mut ingredients, &name, amount).is_err());
+        assert_eq!(ingredients[0].amount, Rational::from_integer(1200));
+
+        // The only ingredient can switch to a rate.
+        ingredients.truncate(1);
+        let amount = set_amount(&mut ingredients, &name, amount).unwrap();
+        assert!(amount.rate && ingredients[0].rate);
+        let (name, amount) = parse_set("set iron plate 900/min").unwrap();
+        let amount = set_amount(&mut ingredients, &name, amount).unwrap();
+        assert_eq!(amount.value, Rational::from_integer(15));
+    }
+
+    #[test]
+    fn set_rejects_invalid_amounts() {
+        let mut ingredients =
+            vec![Ingredient::new("iron plate", Rational::from_integer(1200), 100).unwrap()];
+        for command in ["set iron plate", "set iron plate x", "set 12 stacks"] {
+            assert!(parse_set(command).is_err(), "{command:?}");
+        }
+        for command in [
+            "set iron plate 0",
+            "set iron plate 2-3",
+            "set copper plate 100",
+        ] {
+            let (name, amount) = parse_set(command).unwrap();
+            assert!(
+                set_amount(&mut ingredients, &name, amount).is_err(),
+                "{command:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_cargo_wagon_counts() {
+        match "cars 4, 2,100".parse::<SessionCommand>() {
+            Ok(SessionCommand::Cars(cars)) => assert_eq!(cars, vec![4, 2, 100]),
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn rejects_cargo_wagon_counts_past_the_cap() {
+        for command in [
+            "cars 0",
+            "cars 101",
+            "cars 4,200000000",
+            "cars -1",
+            "cars x",
+        ] {
+            assert!(command.parse::<SessionCommand>().is_err(), "{command:?}");
+        }
+    }
+
+    #[test]
+    fn keeps_history_and_sessions_in_the_data_directory() {
+        let Some(data_dir) = dirs::data_dir() else {
+            return;
+        };
+        let data_dir = data_dir.join("factrain");
+
+        assert_eq!(history_path(), Some(data_dir.join("history.txt")));
+        assert_eq!(
+            session_path("iron-mix_2"),
+            Ok(data_dir.join("sessions").join("iron-mix_2.toml"))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_session_names() {
+        for name in ["../plan", "iron mix", "mix.toml", "~"] {
+            assert!(session_path(name).is_err(), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn parses_session_commands() {
+        match "save iron-mix".parse::<SessionCommand>() {
+            Ok(SessionCommand::Save(name)) => assert_eq!(name, "iron-mix"),
+            other => panic!("{other:?}"),
+        }
+        match "LOAD iron-mix".parse::<SessionCommand>() {
+            Ok(SessionCommand::Load(name)) => assert_eq!(name, "iron-mix"),
+            other => panic!("{other:?}"),
+        }
+        assert!(matches!(
+            "sessions".parse::<SessionCommand>(),
+            Ok(SessionCommand::Sessions)
+        ));
+        for command in ["save", "load", "sessions iron-mix"] {
+            assert!(command.parse::<SessionCommand>().is_err(), "{command:?}");
+        }
+    }
+
+    #[test]
+    fn loading_a_missing_session_replaces_nothing() {
+        let args =
+            Args::try_parse_from(["factrain", "--cars", "3", "--sc", "iron plate=100"]).unwrap();
+        let mut settings = Settings::new(args, &Plan::default()).unwrap();
+        let mut ingredients =
+            vec![Ingredient::new("iron plate", Rational::from_integer(100), 100).unwrap()];
+
+        let name = format!("factrain-test-missing-{}", std::process::id());
+        assert!(load_session(&name, &mut settings, &mut ingredients).is_err());
+        assert_eq!(settings.selection.cars, vec![3]);
+        assert_eq!(ingredients.len(), 1);
+    }
+}
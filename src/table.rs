@@ -0,0 +1,222 @@
+use crate::{
+    args::Settings,
+    compare::Comparison,
+    report::{Format, Report},
+    IngredientRatio,
+};
+
+/// A table with one row per ingredient and one column per configuration, for pasting into spreadsheets and wikis.
+#[derive(Debug, Clone)]
+pub struct Table {
+    /// The header of each column.
+    pub headers: Vec<String>,
+    /// The cells of each row, in the same order as the headers.
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Create a table with an ingredient and stack size column, and one row per ingredient.
+    fn new(item_ratios: &[IngredientRatio]) -> Self {
+        Table {
+            headers: vec!["Ingredient".into(), "Stack size".into()],
+            rows: item_ratios
+                .iter()
+                .map(|item| {
+                    vec![
+                        item.ingredient.name.clone(),
+                        item.ingredient.stack_size.to_string(),
+                    ]
+                })
+                .collect(),
+        }
+    }
+
+    /// Add a column to the table.
+    ///
+    /// # Parameters
+    /// * `header` - The header of the column.
+    /// * `cells` - The cell of each ingredient, in the same order as the rows.
+    fn push_column(&mut self, header: String, cells: impl Iterator<Item = String>) {
+        self.headers.push(header);
+        for (row, cell) in self.rows.iter_mut().zip(cells) {
+            row.push(cell);
+        }
+    }
+
+    /// Tabulate the calculated rows of a report, with one column per configuration (e.g. "ITEMS 160 SLOTS (DC 4 cars)"). Sections the verbosity does not show are left out.
+    pub fn from_report(item_ratios: &[IngredientRatio], settings: &Settings) -> Self {
+        let report = Report::new(item_ratios, &settings.selection, settings.pure);
+        let mut table = Table::new(item_ratios);
+
+        for row in report
+            .rows
+            .iter()
+            .filter(|row| settings.verbosity.shows(row.section))
+        {
+            table.push_column(
+                format!("{} {}", row.section.title(), row.label),
+                (0..item_ratios.len()).map(|index| row.value(item_ratios, index).to_string()),
+            );
+        }
+
+        table
+    }
+
+    /// Tabulate a comparison of train lengths, with one column of slots per train length (e.g. "4-car").
+    pub fn from_comparison(item_ratios: &[IngredientRatio], comparison: &Comparison) -> Self {
+        let mut table = Table::new(item_ratios);
+
+        for (cars, allocation) in &comparison.allocations {
+            table.push_column(
+                format!("{}-car", cars),
+                allocation.counts.iter().map(|count| count.to_string()),
+            );
+        }
+
+        table
+    }
+
+    /// Render the table as CSV, quoting cells where needed.
+    pub fn to_csv(&self) -> String {
+        std::iter::once(&self.headers)
+            .chain(&self.rows)
+            .map(|row| {
+                row.iter()
+                    .map(|cell| csv_cell(cell))
+                    .collect::<Vec<String>>()
+                    .join(",")
+                    + "\n"
+            })
+            .collect()
+    }
+
+    /// Render the table as a Markdown table, with the values right-aligned.
+    pub fn to_markdown(&self) -> String {
+        let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+
+        let mut markdown = line(
+            self.headers
+                .iter()
+                .map(|cell| markdown_cell(cell))
+                .collect(),
+        );
+        markdown += line(
+            (0..self.headers.len())
+                .map(|column| if column == 0 { "---" } else { "---:" }.to_string())
+                .collect(),
+        )
+        .as_str();
+        for row in &self.rows {
+            markdown += line(row.iter().map(|cell| markdown_cell(cell)).collect()).as_str();
+        }
+
+        markdown
+    }
+}
+
+/// Quote a CSV cell if it contains a comma, quote or line break, doubling any quotes inside it.
+fn csv_cell(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+/// Escape the pipes of a Markdown table cell, so that they do not split the cell.
+fn markdown_cell(cell: &str) -> String {
+    cell.replace('|', "\\|")
+}
+
+/// Print the calculated output, or the comparison of train lengths if comparing, as a CSV or Markdown table.
+///
+/// # Parameters
+/// * `item_ratios` - The ingredients to calculate over.
+/// * `settings` - The settings to calculate with, including the table format.
+pub fn print_table(item_ratios: &[IngredientRatio], settings: &Settings) {
+    let selection = &settings.selection;
+    let table = match settings.compare.clone() {
+        Some(range) => {
+            let same_car = selection.same_car && !selection.different_car;
+            Table::from_comparison(
+                item_ratios,
+                &Comparison::new(item_ratios, range, same_car, settings.target_error),
+            )
+        }
+        None => Table::from_report(item_ratios, settings),
+    };
+
+    match settings.format {
+        Format::Markdown => print!("{}", table.to_markdown()),
+        _ => print!("{}", table.to_csv()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{args::Args, calculate_ratios, plan::Plan};
+    use clap::Parser;
+
+    /// A table of the ingredient and stack size columns alone, with the given rows.
+    fn table(rows: &[[&str; 2]]) -> Table {
+        Table {
+            headers: vec!["Ingredient".into(), "Stack size".into()],
+            rows: rows
+                .iter()
+                .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn renders_csv_with_quoted_cells() {
+        let csv = table(&[["iron plate", "100"], ["a, \"b\"", "50"]]).to_csv();
+        assert_eq!(
+            csv,
+            "Ingredient,Stack size\niron plate,100\n\"a, \"\"b\"\"\",50\n"
+        );
+    }
+
+    #[test]
+    fn renders_markdown_with_escaped_pipes() {
+        let markdown = table(&[["iron plate", "100"], ["a|b", "50"]]).to_markdown();
+        assert_eq!(
+            markdown,
+            "| Ingredient | Stack size |\n| --- | ---: |\n| iron plate | 100 |\n| a\\|b | 50 |\n"
+        );
+    }
+
+    #[test]
+    fn tabulates_the_shown_sections() {
+        let args = Args::try_parse_from([
+            "factrain",
+            "--cars",
+            "1",
+            "-q",
+            "iron plate=100",
+            "copper plate=300",
+        ])
+        .unwrap();
+        let settings = Settings::new(args, &Plan::default()).unwrap();
+        let item_ratios = calculate_ratios(&settings.ingredients, false).unwrap();
+
+        let table = Table::from_report(&item_ratios, &settings);
+        assert_eq!(
+            table.headers,
+            vec![
+                "Ingredient",
+                "Stack size",
+                "STACKS 40 SLOTS (1 car)",
+                "INSERTERS 6 INSERTERS (1 car)"
+            ]
+        );
+        assert_eq!(table.rows[0], vec!["iron plate", "100", "10", "2"]);
+        assert_eq!(table.rows[1], vec!["copper plate", "100", "30", "4"]);
+
+        let comparison = Comparison::new(&item_ratios, 1..=2, true, 0.05);
+        let table = Table::from_comparison(&item_ratios, &comparison);
+        assert_eq!(table.headers[2..], ["1-car", "2-car"]);
+        assert_eq!(table.rows[1][2..], ["30", "60"]);
+    }
+}
@@ -0,0 +1,695 @@
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Cell, Paragraph, Row, Table, TableState},
+    DefaultTerminal, Frame,
+};
+
+use crate::{
+    allocation::Allocation,
+    amount::AmountInput,
+    args::Settings,
+    calculate_ratios, check_rate, merge_ingredient,
+    plan::Plan,
+    rational::Rational,
+    report::{chests, Chest},
+    session::parse_ingredient_words,
+    wagon::{self, train_slots, TrainFilters, MAX_CARS, WAGON_COLUMNS, WAGON_SLOTS},
+    Ingredient, IngredientRatio,
+};
+
+/// The colors ingredients are drawn in, by their index. Colors repeat after the last one.
+const COLORS: &[Color] = &[
+    Color::Cyan,
+    Color::Yellow,
+    Color::Magenta,
+    Color::Green,
+    Color::Red,
+    Color::Blue,
+    Color::LightCyan,
+    Color::LightYellow,
+    Color::LightMagenta,
+    Color::LightGreen,
+    Color::LightRed,
+    Color::LightBlue,
+];
+
+/// The keys of the terminal UI, as shown at the bottom of the screen.
+const HELP: &str =
+    "↑/↓ select  a add  enter edit amount  d delete  +/- cars  m mode  c chest  q quit";
+
+/// The color the ingredient at `index` is drawn in.
+fn color(index: usize) -> Color {
+    COLORS[index % COLORS.len()]
+}
+
+/// Prepare a plan to start the terminal UI from, with a train of 1 car if it gives no train lengths and SC filtering if it chooses no wagon filtering, so that there is always something to show.
+pub fn tui_plan(plan: &Plan) -> Plan {
+    let mut plan = plan.clone();
+    if plan.train.cars.is_empty() {
+        plan.train.cars.push(1);
+    }
+    if !plan.train.same_car && !plan.train.different_car {
+        plan.train.same_car = true;
+    }
+    plan
+}
+
+/// What is being typed into the input line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputKind {
+    /// A new ingredient, as "NAME AMOUNT[:STACK]".
+    Add,
+    /// A new amount for the selected ingredient.
+    Amount,
+}
+
+/// A line of text being typed.
+#[derive(Debug, Clone)]
+struct Input {
+    /// What the text is for.
+    kind: InputKind,
+    /// The text typed so far.
+    text: String,
+}
+
+/// Everything calculated from the ingredients and settings, recalculated whenever either changes.
+struct Calculation {
+    /// The ratios of the ingredients.
+    item_ratios: Vec<IngredientRatio>,
+    /// The planned filters of every train.
+    trains: Vec<TrainFilters>,
+    /// The title of each allocation shown side by side (e.g. "DC 4 cars" or "chests"), with its allocation.
+    columns: Vec<(String, Allocation)>,
+}
+
+/// The state of the terminal UI.
+struct App {
+    /// The settings calculated with, changed by keys.
+    settings: Settings,
+    /// The ingredients calculated over, edited in the ingredient table.
+    ingredients: Vec<Ingredient>,
+    /// The selected row of the ingredient table.
+    table: TableState,
+    /// The line of text being typed, if any.
+    input: Option<Input>,
+    /// The result of the last action, and whether it is an error.
+    message: (String, bool),
+    /// Whether to exit.
+    quit: bool,
+}
+
+impl App {
+    /// Start the terminal UI from settings, designing the first train length given.
+    fn new(mut settings: Settings) -> Self {
+        settings.selection.cars.truncate(1);
+        App {
+            ingredients: settings.ingredients.clone(),
+            settings,
+            table: TableState::default().with_selected(Some(0)),
+            input: None,
+            message: (String::new(), false),
+            quit: false,
+        }
+    }
+
+    /// The cargo wagon count of the train being designed.
+    fn cars(&self) -> u32 {
+        self.settings.selection.cars.first().copied().unwrap_or(1)
+    }
+
+    /// Calculate the ratios, wagon filters and allocations of the ingredients.
+    ///
+    /// # Returns
+    /// The calculation as [Ok]\(calculation), or [Err]\(message) if the ingredients or settings cannot be calculated over.
+    fn calculate(&self) -> Result<Calculation, String> {
+        if self.ingredients.is_empty() {
+            return Err("No ingredients yet. Press a to add one.".into());
+        }
+        self.settings.validate()?;
+
+        let item_ratios = calculate_ratios(&self.ingredients, self.settings.exact)?;
+        let selection = &self.settings.selection;
+        let trains = wagon::plan_trains(&item_ratios, selection, self.settings.pure);
+
+        let cars = self.cars();
+        let mut columns: Vec<(String, Allocation)> = trains
+            .iter()
+            .map(|train| {
+                let allocation = if train.same_car {
+                    Allocation::repeated(&item_ratios, WAGON_SLOTS, cars)
+                } else {
+                    Allocation::new(&item_ratios, train_slots(cars))
+                };
+                (train.title(), allocation)
+            })
+            .collect();
+        columns.push((
+            format!("{} chests", selection.chest.name()),
+            Allocation::new(&item_ratios, selection.chest.slots() * chests(cars)),
+        ));
+        // The inserters unload the last train shown, which has DC filtering if it is selected.
+        if let Some(train) = trains.last() {
+            columns.push((
+                "inserters".into(),
+                Allocation::unloading(&item_ratios, train),
+            ));
+        }
+
+        Ok(Calculation {
+            item_ratios,
+            trains,
+            columns,
+        })
+    }
+
+    /// Set the result of the last action.
+    fn tell(&mut self, message: impl Into<String>) {
+        self.message = (message.into(), false);
+    }
+
+    /// Set the error of the last action.
+    fn warn(&mut self, message: impl Into<String>) {
+        self.message = (message.into(), true);
+    }
+
+    /// Handle a key pressed while no text is being typed.
+    fn handle_key(&mut self, code: KeyCode) {
+        self.tell("");
+        let selected = self.table.selected();
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+            KeyCode::Down | KeyCode::Char('j')
+                if selected.is_some_and(|index| index + 1 < self.ingredients.len()) =>
+            {
+                self.table.select_next()
+            }
+            KeyCode::Char('a') => {
+                self.input = Some(Input {
+                    kind: InputKind::Add,
+                    text: String::new(),
+                })
+            }
+            KeyCode::Enter | KeyCode::Char('e') => match selected {
+                Some(index) if index < self.ingredients.len() => {
+                    self.input = Some(Input {
+                        kind: InputKind::Amount,
+                        text: self.ingredients[index].amount.to_string(),
+                    })
+                }
+                _ => self.warn("No ingredient is selected."),
+            },
+            KeyCode::Char('d') | KeyCode::Delete => match selected {
+                Some(index) if index < self.ingredients.len() => {
+                    let removed = self.ingredients.remove(index);
+                    if index >= self.ingredients.len() && index > 0 {
+                        self.table.select(Some(index - 1));
+                    }
+                    self.tell(format!("Removed {}.", removed.name));
+                }
+                _ => self.warn("No ingredient is selected."),
+            },
+            KeyCode::Char('+') | KeyCode::Char('=') | KeyCode::Right => {
+                if self.cars() >= MAX_CARS {
+                    self.warn(format!("Trains can have at most {MAX_CARS} cargo wagons."));
+                } else {
+                    self.settings.selection.cars = vec![self.cars() + 1];
+                }
+            }
+            KeyCode::Char('-') | KeyCode::Left => {
+                self.settings.selection.cars = vec![self.cars().saturating_sub(1).max(1)];
+            }
+            KeyCode::Char('m') => {
+                let selection = &mut self.settings.selection;
+                (selection.same_car, selection.different_car) =
+                    match (selection.same_car, selection.different_car) {
+                        (true, false) => (false, true),
+                        (false, true) => (true, true),
+                        _ => (true, false),
+                    };
+            }
+            KeyCode::Char('c') => {
+                let chest = &mut self.settings.selection.chest;
+                *chest = match chest {
+                    Chest::Wooden => Chest::Iron,
+                    Chest::Iron => Chest::Steel,
+                    Chest::Steel => Chest::Wooden,
+                };
+            }
+            _ => {}
+        }
+    }
+
+    /// Handle a key pressed while text is being typed.
+    fn handle_input_key(&mut self, code: KeyCode) {
+        let Some(input) = self.input.as_mut() else {
+            return;
+        };
+
+        match code {
+            KeyCode::Esc => {
+                self.input = None;
+                self.tell("");
+            }
+            KeyCode::Backspace => {
+                input.text.pop();
+            }
+            KeyCode::Char(c) => input.text.push(c),
+            KeyCode::Enter => {
+                let input = input.clone();
+                match self.submit(&input) {
+                    Ok(message) => {
+                        self.input = None;
+                        self.tell(message);
+                    }
+                    Err(e) => self.warn(e),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Apply the text typed into the input line.
+    ///
+    /// # Returns
+    /// What was done as [Ok]\(message), or [Err]\(message) if the text is invalid, in which case it is left to be corrected.
+    fn submit(&mut self, input: &Input) -> Result<String, String> {
+        match input.kind {
+            InputKind::Add => {
+                let ingredient = parse_ingredient_words(input.text.as_str())?;
+                let name = ingredient.name.clone();
+                let merged = merge_ingredient(&mut self.ingredients, ingredient)?;
+                let index = self.ingredients.iter().position(|item| item.name == name);
+                self.table.select(index);
+                Ok(match merged {
+                    true => format!("Added to the amount of {name}."),
+                    false => format!("Added {name}."),
+                })
+            }
+            InputKind::Amount => {
+                let index = self.table.selected().ok_or("No ingredient is selected.")?;
+                let stack_size = self
+                    .ingredients
+                    .get(index)
+                    .ok_or("No ingredient is selected.")?
+                    .stack_size;
+                let amount = input
+                    .text
+                    .parse::<AmountInput>()?
+                    .to_amount(stack_size)
+                    .ok_or("amount is too large")?;
+                if amount.value <= Rational::ZERO {
+                    return Err("The amount must be more than 0.".into());
+                }
+                check_rate(&self.ingredients, index, amount.rate)?;
+
+                let item = &mut self.ingredients[index];
+                item.amount = amount.value;
+                item.rate = amount.rate;
+                Ok(format!("Set {} to {}.", item.name, amount))
+            }
+        }
+    }
+
+    /// Draw the whole screen.
+    fn draw(&self, frame: &mut Frame) {
+        let [main, status, help] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Fill(1)]).areas(main);
+
+        let calculation = self.calculate();
+        let item_ratios = calculation.as_ref().ok().map(|c| c.item_ratios.as_slice());
+        self.draw_ingredients(frame, left, item_ratios);
+
+        match &calculation {
+            Ok(calculation) => {
+                let allocations_height = calculation.item_ratios.len() as u16 + 4;
+                let [wagons, allocations] =
+                    Layout::vertical([Constraint::Fill(1), Constraint::Length(allocations_height)])
+                        .areas(right);
+                self.draw_wagons(frame, wagons, calculation);
+                draw_allocations(frame, allocations, calculation);
+            }
+            Err(e) => frame.render_widget(
+                Paragraph::new(e.as_str()).block(Block::bordered().title(" Trains ")),
+                right,
+            ),
+        }
+
+        let (message, error) = &self.message;
+        let style = match error {
+            true => Style::new().fg(Color::Red),
+            false => Style::new(),
+        };
+        frame.render_widget(Paragraph::new(message.as_str()).style(style), status);
+
+        let bottom = match &self.input {
+            Some(input) => {
+                let label = match input.kind {
+                    InputKind::Add => "Add NAME AMOUNT[:STACK] > ",
+                    InputKind::Amount => "Amount > ",
+                };
+                Line::from(vec![
+                    Span::styled(label, Style::new().add_modifier(Modifier::BOLD)),
+                    Span::raw(input.text.as_str()),
+                    Span::styled(" ", Style::new().add_modifier(Modifier::REVERSED)),
+                ])
+            }
+            None => Line::styled(HELP, Style::new().add_modifier(Modifier::DIM)),
+        };
+        frame.render_widget(Paragraph::new(bottom), help);
+    }
+
+    /// Draw the table of ingredients, with their ratios if they could be calculated.
+    fn draw_ingredients(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        item_ratios: Option<&[IngredientRatio]>,
+    ) {
+        let rows = self.ingredients.iter().enumerate().map(|(index, item)| {
+            let ratios = item_ratios.and_then(|ratios| ratios.get(index));
+            let percent = |ratio: Option<f64>| match ratio {
+                Some(ratio) => format!("{:.2}%", ratio * 100.0),
+                None => "-".into(),
+            };
+            Row::new(vec![
+                Cell::from(wagon::slot_symbol(index).to_string())
+                    .style(Style::new().fg(color(index))),
+                Cell::from(item.name.as_str()),
+                Cell::from(item.amount.to_string()),
+                Cell::from(item.stack_size.to_string()),
+                Cell::from(percent(ratios.map(|ratio| ratio.ratio))),
+                Cell::from(percent(ratios.map(|ratio| ratio.stack_ratio))),
+            ])
+        });
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(1),
+                Constraint::Fill(1),
+                Constraint::Length(8),
+                Constraint::Length(5),
+                Constraint::Length(7),
+                Constraint::Length(7),
+            ],
+        )
+        .header(
+            Row::new(["", "Item", "Amount", "Stack", "Items", "Stacks"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+        .block(Block::bordered().title(" Ingredients "));
+
+        let mut state = self.table;
+        frame.render_stateful_widget(table, area, &mut state);
+    }
+
+    /// Draw the slot filters of every wagon of every planned train.
+    fn draw_wagons(&self, frame: &mut Frame, area: Rect, calculation: &Calculation) {
+        let mut lines: Vec<Line> = Vec::new();
+        for train in &calculation.trains {
+            lines.push(Line::styled(
+                train.title(),
+                Style::new().add_modifier(Modifier::BOLD),
+            ));
+            for (number, wagon) in train.wagons.iter().enumerate() {
+                for (row, slots) in wagon.slots.chunks(WAGON_COLUMNS).enumerate() {
+                    let label = match row {
+                        0 => format!("W{:<3}", number + 1),
+                        _ => "    ".into(),
+                    };
+                    let mut spans = vec![Span::raw(label)];
+                    spans.extend(slots.iter().map(|slot| match slot {
+                        Some(index) => Span::styled(
+                            format!("[{}]", wagon::slot_symbol(*index)),
+                            Style::new().fg(color(*index)),
+                        ),
+                        None => Span::raw("[ ]"),
+                    }));
+                    lines.push(Line::from(spans));
+                }
+            }
+            lines.push(Line::raw(""));
+        }
+
+        let selection = &self.settings.selection;
+        let mode = match (selection.same_car, selection.different_car) {
+            (true, true) => "SC+DC",
+            (false, true) => "DC",
+            _ => "SC",
+        };
+        let title = format!(
+            " Wagons ({} {}, {}) ",
+            self.cars(),
+            if self.cars() == 1 { "car" } else { "cars" },
+            mode
+        );
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(title)),
+            area,
+        );
+    }
+}
+
+/// Draw the slots of each ingredient in every allocation side by side, with the largest ratio error of each allocation below.
+fn draw_allocations(frame: &mut Frame, area: Rect, calculation: &Calculation) {
+    let item_ratios = &calculation.item_ratios;
+    let rows = item_ratios.iter().enumerate().map(|(index, item)| {
+        let mut cells =
+            vec![Cell::from(item.ingredient.name.as_str()).style(Style::new().fg(color(index)))];
+        cells.extend(
+            calculation
+                .columns
+                .iter()
+                .map(|(_, allocation)| Cell::from(allocation.counts[index].to_string())),
+        );
+        Row::new(cells)
+    });
+
+    let mut header = vec![String::from("Slots")];
+    header.extend(calculation.columns.iter().map(|(title, _)| title.clone()));
+    let mut footer = vec![String::from("max error")];
+    footer.extend(
+        calculation.columns.iter().map(|(_, allocation)| {
+            format!("{:.2}%", allocation.max_ratio_error(item_ratios) * 100.0)
+        }),
+    );
+
+    let mut widths = vec![Constraint::Fill(1)];
+    widths.extend(calculation.columns.iter().map(|_| Constraint::Length(14)));
+
+    let table = Table::new(rows, widths)
+        .header(Row::new(header).style(Style::new().add_modifier(Modifier::BOLD)))
+        .footer(Row::new(footer).style(Style::new().add_modifier(Modifier::DIM)))
+        .block(Block::bordered().title(" Allocations "));
+    frame.render_widget(table, area);
+}
+
+/// Run the terminal UI until the user quits, recalculating on every change.
+fn run_app(terminal: &mut DefaultTerminal, app: &mut App) -> std::io::Result<()> {
+    while !app.quit {
+        terminal.draw(|frame| app.draw(frame))?;
+
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            // The terminal is in raw mode, so Ctrl+C arrives as a key rather than interrupting the program.
+            if key.modifiers.contains(KeyModifiers::CONTROL) {
+                match key.code {
+                    KeyCode::Char('c') => app.quit = true,
+                    KeyCode::Char('u') => {
+                        if let Some(input) = app.input.as_mut() {
+                            input.text.clear();
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            match app.input {
+                Some(_) => app.handle_input_key(key.code),
+                None => app.handle_key(key.code),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Open a full-screen terminal UI for designing a station: an editable table of ingredients beside the live wagon filters and slot allocations of the train, with keys to change the train length, wagon filtering and chest type.
+///
+/// # Parameters
+/// * `settings` - The settings to start from, including the ingredients.
+///
+/// # Returns
+/// The ingredients and settings as they were left when the user quit as [Ok]\((settings, ingredients)), or [Err]\(error) if the terminal could not be used.
+pub fn run(settings: Settings) -> std::io::Result<(Settings, Vec<Ingredient>)> {
+    // One train is designed at a time, starting from the first train length given.
+    let mut app = App::new(settings);
+
+    let mut terminal = ratatui::try_init()?;
+    let result = run_app(&mut terminal, &mut app);
+    ratatui::restore();
+    result?;
+
+    Ok((app.settings, app.ingredients))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Args;
+    use clap::Parser;
+
+    /// An app started from a plan with the given ingredients and nothing else, as the tui command does.
+    fn app(ingredients: &[&str]) -> App {
+        let plan = tui_plan(&Plan::default());
+        let args = ["factrain", "--sc"]
+            .into_iter()
+            .chain(ingredients.iter().copied());
+        let settings = Settings::new(Args::try_parse_from(args).unwrap(), &plan).unwrap();
+        App::new(settings)
+    }
+
+    /// Type a line of text of the given kind and submit it.
+    fn submit(app: &mut App, kind: InputKind, text: &str) -> Result<String, String> {
+        app.submit(&Input {
+            kind,
+            text: text.into(),
+        })
+    }
+
+    /// The name and amount of every ingredient.
+    fn amounts(app: &App) -> Vec<(String, String)> {
+        app.ingredients
+            .iter()
+            .map(|item| (item.name.clone(), item.amount.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn plans_start_with_a_train() {
+        let plan = tui_plan(&Plan::default());
+        assert_eq!(plan.train.cars, vec![1]);
+        assert!(plan.train.same_car && !plan.train.different_car);
+
+        let mut given = Plan::default();
+        given.train.cars = vec![4, 8];
+        given.train.different_car = true;
+        let plan = tui_plan(&given);
+        assert_eq!(plan.train.cars, vec![4, 8]);
+        assert!(!plan.train.same_car && plan.train.different_car);
+    }
+
+    #[test]
+    fn adds_and_merges_ingredients() {
+        let mut app = app(&[]);
+        assert!(app.calculate().is_err());
+
+        assert_eq!(
+            submit(&mut app, InputKind::Add, "iron-plate 1200"),
+            Ok("Added iron plate.".into())
+        );
+        assert_eq!(
+            submit(&mut app, InputKind::Add, "copper plate 800"),
+            Ok("Added copper plate.".into())
+        );
+        assert_eq!(app.table.selected(), Some(1));
+        assert_eq!(
+            submit(&mut app, InputKind::Add, "iron plate 300"),
+            Ok("Added to the amount of iron plate.".into())
+        );
+        assert_eq!(app.table.selected(), Some(0));
+        assert_eq!(
+            amounts(&app),
+            vec![
+                ("iron plate".into(), "1500".into()),
+                ("copper plate".into(), "800".into())
+            ]
+        );
+        assert!(app.calculate().is_ok());
+    }
+
+    #[test]
+    fn edits_the_selected_amount() {
+        let mut app = app(&["iron plate=1200", "copper plate=800"]);
+        app.table.select(Some(1));
+        assert_eq!(
+            submit(&mut app, InputKind::Amount, "2 * 300"),
+            Ok("Set copper plate to 600 items.".into())
+        );
+        assert_eq!(app.ingredients[1].amount, Rational::from_integer(600));
+        assert_eq!(app.ingredients[0].amount, Rational::from_integer(1200));
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        let mut app = app(&["iron plate=1200"]);
+        for text in ["", "iron plate", "iron plate -5", "iron plate 1/0"] {
+            assert!(submit(&mut app, InputKind::Add, text).is_err(), "{text:?}");
+        }
+        for text in ["", "0", "-100", "abc"] {
+            assert!(
+                submit(&mut app, InputKind::Amount, text).is_err(),
+                "{text:?}"
+            );
+        }
+        assert_eq!(amounts(&app), vec![("iron plate".into(), "1200".into())]);
+
+        // A rejected line is left to be corrected.
+        app.input = Some(Input {
+            kind: InputKind::Amount,
+            text: "0".into(),
+        });
+        app.handle_input_key(KeyCode::Enter);
+        assert!(app.input.is_some());
+        assert!(app.message.1);
+    }
+
+    #[test]
+    fn deletes_the_selected_ingredient() {
+        let mut app = app(&["iron plate=1200", "copper plate=800", "steel=100"]);
+        app.handle_key(KeyCode::Down);
+        app.handle_key(KeyCode::Down);
+        app.handle_key(KeyCode::Down);
+        assert_eq!(app.table.selected(), Some(2));
+
+        app.handle_key(KeyCode::Char('d'));
+        assert_eq!(app.message, ("Removed steel plate.".into(), false));
+        assert_eq!(app.table.selected(), Some(1));
+        app.handle_key(KeyCode::Up);
+        app.handle_key(KeyCode::Char('d'));
+        assert_eq!(app.table.selected(), Some(0));
+        assert_eq!(amounts(&app), vec![("copper plate".into(), "800".into())]);
+
+        app.handle_key(KeyCode::Char('d'));
+        assert!(app.ingredients.is_empty());
+        app.handle_key(KeyCode::Char('d'));
+        assert!(app.message.1);
+    }
+
+    #[test]
+    fn keeps_the_train_length_in_range() {
+        let mut app = app(&["iron plate=1200"]);
+        app.handle_key(KeyCode::Char('-'));
+        assert_eq!(app.cars(), 1);
+
+        app.settings.selection.cars = vec![MAX_CARS - 1];
+        app.handle_key(KeyCode::Char('+'));
+        assert_eq!(app.cars(), MAX_CARS);
+        app.handle_key(KeyCode::Char('+'));
+        assert_eq!(app.cars(), MAX_CARS);
+        assert!(app.message.1);
+        assert!(app.calculate().is_ok());
+    }
+}
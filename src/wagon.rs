@@ -0,0 +1,346 @@
+use crate::{
+    allocation::{apportion, Allocation},
+    report::Selection,
+    IngredientRatio,
+};
+
+/// The number of inventory slots in a vanilla cargo wagon.
+pub const WAGON_SLOTS: u32 = 40;
+
+/// The number of columns in a cargo wagon's inventory grid.
+pub const WAGON_COLUMNS: usize = 10;
+
+/// The number of inserters (and chests) beside each cargo wagon along one side of an unloading station. The tiles between wagons are left empty.
+pub const WAGON_INSERTERS: u32 = 6;
+
+/// The largest number of cargo wagons a train can be calculated over, well past any train that fits in a base.
+pub const MAX_CARS: u32 = 100;
+
+/// The number of slots across every cargo wagon of a train with `cars` cargo wagons.
+pub fn train_slots(cars: u32) -> u32 {
+    WAGON_SLOTS.saturating_mul(cars)
+}
+
+/// The filters set on every slot of a single cargo wagon.
+#[derive(Debug, Clone)]
+pub struct WagonFilter {
+    /// The index of the ingredient each slot is filtered to, in inventory order (left to right, top to bottom). [None] if the slot is left unfiltered.
+    pub slots: Vec<Option<usize>>,
+}
+
+impl WagonFilter {
+    /// Whether every slot of this wagon is filtered to the same ingredient.
+    pub fn is_pure(&self) -> bool {
+        match self.slots.first() {
+            Some(Some(first)) => self.slots.iter().all(|slot| *slot == Some(*first)),
+            _ => false,
+        }
+    }
+
+    /// The number of slots filtered to the ingredient at `index`.
+    pub fn count(&self, index: usize) -> u32 {
+        self.slots
+            .iter()
+            .filter(|slot| **slot == Some(index))
+            .count() as u32
+    }
+
+    /// The number of items of each ingredient this wagon holds when full.
+    pub fn items(&self, item_ratios: &[IngredientRatio]) -> Vec<f64> {
+        item_ratios
+            .iter()
+            .enumerate()
+            .map(|(index, item)| self.count(index) as f64 * item.ingredient.stack_size as f64)
+            .collect()
+    }
+
+    /// Hand out the inserters unloading this wagon between the items it holds, by the number of each it holds, so that every item the wagon holds gets at least one inserter (as far as there are inserters to go round).
+    ///
+    /// # Parameters
+    /// * `item_ratios` - The ingredients the filters were planned for.
+    /// * `inserters` - The number of inserters beside the wagon.
+    ///
+    /// # Returns
+    /// The number of inserters given to each ingredient.
+    pub fn inserters(&self, item_ratios: &[IngredientRatio], inserters: u32) -> Vec<u32> {
+        let items = self.items(item_ratios);
+        let mut counts: Vec<u32> = items.iter().map(|items| u32::from(*items > 0.0)).collect();
+        let held: u32 = counts.iter().sum();
+
+        // Give the inserters left once every held item has one by the number of items held.
+        let rest = apportion(&items, inserters.saturating_sub(held));
+        for (count, extra) in counts.iter_mut().zip(rest) {
+            *count += extra;
+        }
+
+        counts
+    }
+}
+
+/// Plan the slot filters of every cargo wagon on a train.
+///
+/// # Parameters
+/// * `item_ratios` - The ingredients and their ratios to be loaded onto the train.
+/// * `cars` - The number of cargo wagons on the train.
+/// * `same_car` - Whether each wagon must follow the same filter ("SC" filtering). If false, each wagon is free to follow its own filter ("DC" filtering).
+/// * `pure` - Whether to dedicate whole wagons to a single ingredient where the ingredient fills them. Only applies to DC filtering.
+///
+/// # Returns
+/// The filters of each wagon, from the front of the train to the back.
+pub fn plan_filters(
+    item_ratios: &[IngredientRatio],
+    cars: u32,
+    same_car: bool,
+    pure: bool,
+) -> Vec<WagonFilter> {
+    if same_car {
+        // Each wagon follows the same filter, so only one wagon's worth of slots needs apportioning.
+        let counts = Allocation::new(item_ratios, WAGON_SLOTS).counts;
+        let wagon = WagonFilter {
+            slots: fill_slots(&counts),
+        };
+        return vec![wagon; cars as usize];
+    }
+
+    let mut counts = Allocation::new(item_ratios, train_slots(cars)).counts;
+    let mut wagons: Vec<WagonFilter> = Vec::new();
+
+    if pure {
+        // Take out every whole wagon's worth of each ingredient as a pure wagon.
+        for (index, count) in counts.iter_mut().enumerate() {
+            while *count >= WAGON_SLOTS {
+                *count -= WAGON_SLOTS;
+                wagons.push(WagonFilter {
+                    slots: vec![Some(index); WAGON_SLOTS as usize],
+                });
+            }
+        }
+    }
+
+    // Deal the remaining slots out between the mixed wagons one at a time, so that each ingredient is spread as evenly as possible between them.
+    let mixed_cars = cars as usize - wagons.len();
+    let mut mixed: Vec<WagonFilter> = vec![WagonFilter { slots: Vec::new() }; mixed_cars];
+    for (position, slot) in fill_slots(&counts).into_iter().enumerate() {
+        mixed[position % mixed_cars].slots.push(slot);
+    }
+
+    wagons.extend(mixed);
+    wagons
+}
+
+/// The planned filters of every wagon of one train.
+#[derive(Debug, Clone)]
+pub struct TrainFilters {
+    /// The number of cargo wagons on the train.
+    pub cars: u32,
+    /// Whether each wagon follows the same filter ("SC" filtering).
+    pub same_car: bool,
+    /// The filters of each wagon, from the front of the train to the back.
+    pub wagons: Vec<WagonFilter>,
+}
+
+impl TrainFilters {
+    /// The title of the train configuration (e.g. "DC 4 cars").
+    pub fn title(&self) -> String {
+        let mode = if self.same_car { "SC" } else { "DC" };
+        format!("{} {} cars", mode, self.cars)
+    }
+
+    /// The number of slots filtered to the ingredient at `index` across every wagon of the train.
+    pub fn count(&self, index: usize) -> u32 {
+        self.wagons.iter().map(|wagon| wagon.count(index)).sum()
+    }
+
+    /// The number of inserters unloading each ingredient along one side of the train's unloading station, with the inserters beside each wagon handed out between the items that wagon holds (see [WagonFilter::inserters]).
+    pub fn inserters(&self, item_ratios: &[IngredientRatio]) -> Vec<u32> {
+        let mut counts = vec![0; item_ratios.len()];
+        for wagon in &self.wagons {
+            for (count, inserters) in counts
+                .iter_mut()
+                .zip(wagon.inserters(item_ratios, WAGON_INSERTERS))
+            {
+                *count += inserters;
+            }
+        }
+        counts
+    }
+}
+
+/// Plan the filters of every selected train. Single-wagon trains and SC filtering are planned with SC filtering, and multi-wagon trains with DC filtering if selected.
+///
+/// # Parameters
+/// * `item_ratios` - The ingredients to be loaded onto the trains.
+/// * `selection` - The train configurations to plan.
+/// * `pure` - Whether to dedicate whole wagons to a single ingredient where the ingredient fills them.
+pub fn plan_trains(
+    item_ratios: &[IngredientRatio],
+    selection: &Selection,
+    pure: bool,
+) -> Vec<TrainFilters> {
+    let mut trains: Vec<TrainFilters> = Vec::new();
+
+    for &cars in &selection.cars {
+        if cars == 1 || selection.same_car {
+            trains.push(TrainFilters {
+                cars,
+                same_car: true,
+                wagons: plan_filters(item_ratios, cars, true, false),
+            });
+        }
+
+        if cars > 1 && selection.different_car {
+            trains.push(TrainFilters {
+                cars,
+                same_car: false,
+                wagons: plan_filters(item_ratios, cars, false, pure),
+            });
+        }
+    }
+
+    trains
+}
+
+/// Lay out slot counts as a list of slot filters, with each ingredient's slots next to each other.
+pub fn fill_slots(counts: &[u32]) -> Vec<Option<usize>> {
+    counts
+        .iter()
+        .enumerate()
+        .flat_map(|(index, count)| std::iter::repeat_n(Some(index), *count as usize))
+        .collect()
+}
+
+/// The symbol used to represent the ingredient at `index` in a wagon grid.
+pub fn slot_symbol(index: usize) -> char {
+    const SYMBOLS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+    SYMBOLS
+        .get(index)
+        .map(|symbol| *symbol as char)
+        .unwrap_or('?')
+}
+
+/// Print the filters of each wagon as a grid of slots matching the in-game cargo wagon inventory, followed by a legend of the symbols used.
+///
+/// # Parameters
+/// * `item_ratios` - The ingredients the filters were planned from.
+/// * `wagons` - The planned wagon filters.
+/// * `title` - The title of the train configuration (e.g. "DC 4 cars").
+pub fn print_wagon_grids(item_ratios: &[IngredientRatio], wagons: &[WagonFilter], title: &str) {
+    println!("{:=^80}", format!(" FILTERS ({}) ", title));
+
+    for (number, wagon) in wagons.iter().enumerate() {
+        let kind = if wagon.is_pure() { "PURE" } else { "MIXED" };
+        println!("{:-^80}", format!(" WAGON {} ({}) ", number + 1, kind));
+
+        for row in wagon.slots.chunks(WAGON_COLUMNS) {
+            let cells: String = row
+                .iter()
+                .map(|slot| match slot {
+                    Some(index) => format!("[{}]", slot_symbol(*index)),
+                    None => "[ ]".into(),
+                })
+                .collect();
+            println!("{:^80}", cells);
+        }
+    }
+
+    println!("{:-^80}", " LEGEND ");
+    for (index, item) in item_ratios.iter().enumerate() {
+        let slots: u32 = wagons.iter().map(|wagon| wagon.count(index)).sum();
+        println!(
+            "{:>40} ---- {} ({} slots)",
+            slot_symbol(index),
+            item.ingredient.name,
+            slots
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{calculate_ratios, rational::Rational, Ingredient};
+
+    /// The item ratios of ingredients given as (amount, stack size) pairs.
+    fn ratios(ingredients: &[(i128, u32)]) -> Vec<IngredientRatio> {
+        let ingredients: Vec<Ingredient> = ingredients
+            .iter()
+            .enumerate()
+            .map(|(index, (amount, stack_size))| {
+                Ingredient::new(
+                    &format!("item {index}"),
+                    Rational::from_integer(*amount),
+                    *stack_size,
+                )
+                .unwrap()
+            })
+            .collect();
+        calculate_ratios(&ingredients, false).unwrap()
+    }
+
+    /// Check that a train has a wagon for each car, with every slot of each wagon filtered.
+    fn assert_full(wagons: &[WagonFilter], cars: u32) {
+        assert_eq!(wagons.len(), cars as usize);
+        for wagon in wagons {
+            assert_eq!(wagon.slots.len(), WAGON_SLOTS as usize);
+            assert!(wagon.slots.iter().all(Option::is_some));
+        }
+    }
+
+    #[test]
+    fn same_car_wagons_share_a_filter() {
+        let item_ratios = ratios(&[(3, 100), (1, 100)]);
+        let wagons = plan_filters(&item_ratios, 3, true, false);
+        assert_full(&wagons, 3);
+        for wagon in &wagons {
+            assert_eq!((wagon.count(0), wagon.count(1)), (30, 10));
+            assert!(!wagon.is_pure());
+        }
+    }
+
+    #[test]
+    fn pure_filters_with_every_wagon_pure() {
+        let item_ratios = ratios(&[(1, 100), (1, 100)]);
+        let wagons = plan_filters(&item_ratios, 2, false, true);
+        assert_full(&wagons, 2);
+        assert!(wagons.iter().all(WagonFilter::is_pure));
+        assert_eq!((wagons[0].count(0), wagons[1].count(1)), (40, 40));
+
+        let item_ratios = ratios(&[(2, 50), (1, 50)]);
+        let wagons = plan_filters(&item_ratios, 3, false, true);
+        assert_full(&wagons, 3);
+        assert!(wagons.iter().all(WagonFilter::is_pure));
+        let counts: Vec<(u32, u32)> = wagons
+            .iter()
+            .map(|wagon| (wagon.count(0), wagon.count(1)))
+            .collect();
+        assert_eq!(counts, vec![(40, 0), (40, 0), (0, 40)]);
+
+        let item_ratios = ratios(&[(1, 200)]);
+        let wagons = plan_filters(&item_ratios, 4, false, true);
+        assert_full(&wagons, 4);
+        assert!(wagons.iter().all(WagonFilter::is_pure));
+    }
+
+    #[test]
+    fn pure_filters_deal_the_rest_to_mixed_wagons() {
+        let item_ratios = ratios(&[(5, 100), (3, 100)]);
+        let wagons = plan_filters(&item_ratios, 4, false, true);
+        assert_full(&wagons, 4);
+        let counts: Vec<(u32, u32)> = wagons
+            .iter()
+            .map(|wagon| (wagon.count(0), wagon.count(1)))
+            .collect();
+        assert_eq!(counts, vec![(40, 0), (40, 0), (0, 40), (20, 20)]);
+        assert_eq!(wagons.iter().filter(|wagon| wagon.is_pure()).count(), 3);
+    }
+
+    #[test]
+    fn different_car_filters_spread_each_ingredient() {
+        let item_ratios = ratios(&[(5, 100), (3, 100)]);
+        let wagons = plan_filters(&item_ratios, 4, false, false);
+        assert_full(&wagons, 4);
+        for wagon in &wagons {
+            assert_eq!((wagon.count(0), wagon.count(1)), (25, 15));
+        }
+    }
+}